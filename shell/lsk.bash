@@ -0,0 +1,37 @@
+# lsk shell integration for bash.
+#
+# Add this to ~/.bashrc:
+#
+#     eval "$(lsk init bash)"
+#
+# Then `w` inside lsk changes this shell's directory to the viewed dir,
+# and `r <keys>` leaves the returned paths in the $lsk_paths array.
+lsk() {
+    # Subcommands don't return anything to the shell.
+    case "$1" in
+        is-dir|get-file|ls|init|config)
+            command lsk "$@"
+            return
+            ;;
+    esac
+
+    local choosedir output dir ret
+    choosedir="$(mktemp)" || return
+    output="$(mktemp)" || { rm -f -- "$choosedir"; return 1; }
+
+    command lsk --choosedir "$choosedir" --output "$output" "$@"
+    ret=$?
+
+    if [ -s "$output" ]; then
+        mapfile -t lsk_paths < "$output"
+        printf '%s\n' "${lsk_paths[@]}"
+    fi
+    dir="$(cat -- "$choosedir")"
+    rm -f -- "$choosedir" "$output"
+
+    if [ -n "$dir" ] && [ "$dir" != "$PWD" ]; then
+        cd -- "$dir" || return
+    fi
+
+    return $ret
+}
@@ -0,0 +1,34 @@
+# lsk shell integration for fish.
+#
+# Add this to ~/.config/fish/config.fish:
+#
+#     lsk init fish | source
+#
+# Then `w` inside lsk changes this shell's directory to the viewed dir,
+# and `r <keys>` leaves the returned paths in the $lsk_paths list.
+function lsk --wraps lsk
+    # Subcommands don't return anything to the shell.
+    if contains -- "$argv[1]" is-dir get-file ls init config
+        command lsk $argv
+        return
+    end
+
+    set -l choosedir (mktemp); or return
+    set -l output (mktemp); or begin; rm -f -- $choosedir; return 1; end
+
+    command lsk --choosedir $choosedir --output $output $argv
+    set -l ret $status
+
+    if test -s $output
+        set -g lsk_paths (cat $output)
+        printf '%s\n' $lsk_paths
+    end
+    set -l dir (cat $choosedir)
+    rm -f -- $choosedir $output
+
+    if test -n "$dir"; and test "$dir" != "$PWD"
+        cd $dir; or return
+    end
+
+    return $ret
+end
@@ -1,11 +1,20 @@
 extern crate seahorse;
 extern crate ls_key;
+extern crate serde;
+extern crate serde_json;
 
 use std::path::{Path, PathBuf};
 use std::env;
-use ls_key::{list, app};
-use list::{List, is_dir};
+use std::fs::metadata;
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::time::UNIX_EPOCH;
+use ls_key::{list, app, terminal, separate_paths, ShellOutput};
+use ls_key::bookmarks::Bookmarks;
+use ls_key::config::{self, Config};
+use list::{List, Sort};
 use seahorse::{App, Command, Context, Flag, FlagType};
+use serde::Serialize;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -16,16 +25,127 @@ fn main() {
         .usage("cli [path]")
         .action(default_action)
         .flag(Flag::new("all", "cli [path] --all(-a)", FlagType::Bool).alias("a"))
+        .flag(Flag::new("no-all", "cli [path] --no-all", FlagType::Bool))
         .flag(Flag::new("fuzzy-cmd", "cli [path] --fuzzy-cmd(-c)", FlagType::String).alias("c"))
         .flag(Flag::new("fuzzy-find", "cli [path] --fuzzy-find(-f)", FlagType::String).alias("f"))
         .flag(Flag::new("fuzzy-dir", "cli [path] --fuzzy-dir(-d)", FlagType::String).alias("d"))
+        .flag(Flag::new("sort", "cli [path] --sort name|size|time", FlagType::String))
+        .flag(Flag::new("reverse", "cli [path] --reverse(-r)", FlagType::Bool).alias("r"))
+        .flag(Flag::new("choosedir", "cli [path] --choosedir [file]", FlagType::String))
+        .flag(Flag::new("output", "cli [path] --output [file]", FlagType::String))
+        .flag(Flag::new("pick", "cli [path] --pick", FlagType::Bool))
+        .flag(Flag::new("print0", "cli [path] --pick --print0(-0)", FlagType::Bool).alias("0"))
+        .flag(Flag::new("bookmark", "cli --bookmark [name]", FlagType::String).alias("b"))
+        .flag(Flag::new("tree", "cli [path] --tree", FlagType::Bool))
+        .flag(Flag::new("level", "cli [path] --tree --level(-L) [depth]", FlagType::Int).alias("L"))
+        .flag(Flag::new("du", "cli [path] --du", FlagType::Bool))
+        .flag(Flag::new("mouse", "cli [path] --mouse", FlagType::Bool))
         .command(is_dir_command())
-        .command(get_file_by_key_command());
+        .command(get_file_by_key_command())
+        .command(ls_command())
+        .command(init_command())
+        .command(config_command());
 
     app.run(args);
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Plain,
+    Json,
+    Ndjson,
+}
+
+fn format_flag(c: &Context) -> Format {
+    if c.bool_flag("json") {
+        return Format::Json;
+    }
+
+    match c.string_flag("format").as_deref() {
+        None | Some("plain") => Format::Plain,
+        Some("json") => Format::Json,
+        Some("ndjson") => Format::Ndjson,
+        Some(format) => {
+            eprintln!("unknown format '{}', expected json, ndjson or plain", format);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn format_usage(cmd: &str) -> Flag {
+    Flag::new("format".to_string(), format!("cli {} --format json|ndjson|plain", cmd), FlagType::String)
+}
+
+// One per path in --format json|ndjson. Fields that don't apply are null.
+#[derive(Debug, Default, Serialize)]
+struct Record {
+    path: Option<String>,
+    key: Option<usize>,
+    #[serde(rename = "type")]
+    file_type: Option<&'static str>,
+    size: Option<u64>,
+    // Seconds since the unix epoch.
+    mtime: Option<u64>,
+    error: Option<String>,
+}
+
+impl Record {
+    fn new<P: AsRef<Path>>(path: P, key: Option<usize>) -> Self {
+        let path = path.as_ref();
+        let mut record = Record {
+            path: Some(path.to_string_lossy().into_owned()),
+            key,
+            ..Default::default()
+        };
+
+        match metadata(path) {
+            Ok(md) => {
+                record.file_type = Some(if md.is_dir() { "dir" } else { "file" });
+                record.size = Some(md.len());
+                record.mtime = md.modified().ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map(|since| since.as_secs());
+            },
+            Err(e) => record.error = Some(e.to_string())
+        }
+
+        record
+    }
+
+    fn error<T: Into<String>>(key: Option<usize>, error: T) -> Self {
+        Record {
+            key,
+            error: Some(error.into()),
+            ..Default::default()
+        }
+    }
+}
+
+// A json listing is one array; ndjson is a line per record either way.
+fn print_records(format: Format, records: &[Record], listing: bool) {
+    match format {
+        Format::Json if listing => println!("{}", serde_json::to_string(records).unwrap()),
+        Format::Json | Format::Ndjson => {
+            for record in records {
+                println!("{}", serde_json::to_string(record).unwrap());
+            }
+        },
+        Format::Plain => ()
+    }
+}
+
+// Exit codes: 1 for a plain "no" (no such key, not a dir), 2 for errors.
+fn fail(format: Format, record: Record, code: i32) -> ! {
+    match format {
+        Format::Plain => eprintln!("{}", record.error.as_deref().unwrap_or("error")),
+        _ => print_records(format, &[record], false)
+    }
+
+    std::process::exit(code)
+}
+
 fn get_file_by_key_action(c: &Context) {
+    let format = format_flag(c);
     let mut args = c.args.iter();
     let mut path = "";
     let mut key = "";
@@ -49,18 +169,39 @@ fn get_file_by_key_action(c: &Context) {
         use_path = env::current_dir().unwrap();
     }
 
-    let list = List::new(use_path)
-        .list_skip_hidden()
-        .unwrap();
+    // Same settings as the interactive view, so the keys line up.
+    let config = load_config();
+    let mut list = List::new(use_path);
+    list.sort = sort_flags(c, format, config.sort);
+    let list = if all_flag(c, config.all) {
+        list.list_include_hidden()
+    } else {
+        list.list_skip_hidden()
+    }.unwrap_or_else(|e| fail(format, Record::error(None, e.to_string()), 2));
 
-    let key: usize = key.parse().expect("The key must parseable into an integer.");
+    let key: usize = match key.parse() {
+        Ok(key) => key,
+        Err(_) => fail(format, Record::error(None, format!("the key must be an integer, not '{}'", key)), 2)
+    };
     let res = list.get_file_by_key(key, true);
-    if let Some(x) = res {
-       println!("{}", x.to_str().unwrap());
+    match res {
+        Ok(x) => {
+            if format == Format::Plain {
+                // The path's own bytes, which needn't be UTF-8.
+                let mut stdout = std::io::stdout();
+                stdout.write_all(x.as_os_str().as_bytes())
+                    .and_then(|_| stdout.write_all(b"\n"))
+                    .unwrap_or_else(|e| fail(format, Record::error(Some(key), e.to_string()), 2));
+            } else {
+                print_records(format, &[Record::new(x, Some(key))], false);
+            }
+        },
+        Err(e) => fail(format, Record::error(Some(key), e.to_string()), 1)
     }
 }
 
 fn is_dir_action(c: &Context) {
+    let format = format_flag(c);
     let mut args = c.args.iter();
     let arg_count = args.clone().count();
     let path = match arg_count {
@@ -76,12 +217,20 @@ fn is_dir_action(c: &Context) {
         use_path = env::current_dir().unwrap();
     }
 
-    let res = is_dir(use_path);
+    let record = Record::new(use_path, None);
+    if record.error.is_some() {
+        fail(format, record, 2);
+    }
 
-    if res {
-       println!("0");
-    } else {
-       println!("1");
+    let res = record.file_type == Some("dir");
+    // Plain keeps printing the answer as well, as scripts read it.
+    if format == Format::Plain {
+        println!("{}", if res { 0 } else { 1 });
+    }
+    print_records(format, &[record], false);
+
+    if !res {
+        std::process::exit(1);
     }
 }
 
@@ -90,6 +239,7 @@ fn is_dir_command() -> Command {
         .name("is-dir")
         .usage("cli is-dir [dir]")
         .action(is_dir_action)
+        .flag(format_usage("is-dir [dir]"))
 }
 
 fn get_file_by_key_command() -> Command {
@@ -97,6 +247,154 @@ fn get_file_by_key_command() -> Command {
         .name("get-file")
         .usage("cli get-file [dir] [key]")
         .action(get_file_by_key_action)
+        .flag(Flag::new("all", "cli get-file [dir] [key] --all(-a)", FlagType::Bool).alias("a"))
+        .flag(Flag::new("no-all", "cli get-file [dir] [key] --no-all", FlagType::Bool))
+        .flag(Flag::new("sort", "cli get-file [dir] [key] --sort name|size|time", FlagType::String))
+        .flag(Flag::new("reverse", "cli get-file [dir] [key] --reverse(-r)", FlagType::Bool).alias("r"))
+        .flag(format_usage("get-file [dir] [key]"))
+}
+
+fn sort_flags(c: &Context, format: Format, mut sort: Sort) -> Sort {
+    if let Some(by) = c.string_flag("sort") {
+        sort.by = by.parse().unwrap_or_else(|e: String| fail(format, Record::error(None, e), 2));
+    }
+    if c.bool_flag("reverse") {
+        sort.reverse = true;
+    }
+
+    sort
+}
+
+// --all and --no-all each win over the config's all.
+fn all_flag(c: &Context, all: bool) -> bool {
+    if c.bool_flag("no-all") {
+        false
+    } else {
+        all || c.bool_flag("all")
+    }
+}
+
+// Same listing, same keys, as the interactive view.
+fn ls_action(c: &Context) {
+    let format = format_flag(c);
+    let use_path = match c.args.first() {
+        Some(path) => PathBuf::from(path),
+        None => env::current_dir().unwrap()
+    };
+
+    if let Err(e) = metadata(&use_path) {
+        fail(format, Record { path: Some(use_path.to_string_lossy().into_owned()), ..Record::error(None, e.to_string()) }, 2);
+    }
+
+    let config = load_config();
+    let mut list = List::new(use_path);
+    list.sort = sort_flags(c, format, config.sort);
+    let list = if all_flag(c, config.all) {
+        list.list_include_hidden()
+    } else {
+        list.list_skip_hidden()
+    }.unwrap_or_else(|e| fail(format, Record::error(None, e.to_string()), 2));
+
+    // Key 0 is going up a dir, not an entry.
+    let rows: Vec<(PathBuf, Record)> = list::order_and_sort_list(&list, true)
+        .into_iter()
+        .skip(1)
+        .map(|entry| {
+            let path = list.parent_path.join(&entry.path);
+            (path.clone(), Record::new(path, entry.key))
+        })
+        .collect();
+
+    if format == Format::Plain {
+        let mut stdout = std::io::stdout();
+        for (path, record) in rows {
+            stdout.write_all(&plain_row(record.key.unwrap(), &path, record.file_type.unwrap_or("file")))
+                .unwrap_or_else(|e| fail(format, Record::error(None, e.to_string()), 2));
+        }
+    } else {
+        let records: Vec<Record> = rows.into_iter().map(|(_, record)| record).collect();
+        print_records(format, &records, true);
+    }
+}
+
+// key<TAB>path<TAB>type, the path in its own bytes. A tab, newline or
+// backslash in it is written \t, \n or \\, so a row stays one line of three fields.
+fn plain_row(key: usize, path: &Path, file_type: &str) -> Vec<u8> {
+    let mut row = format!("{}\t", key).into_bytes();
+    for byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'\t' => row.extend_from_slice(b"\\t"),
+            b'\n' => row.extend_from_slice(b"\\n"),
+            b'\\' => row.extend_from_slice(b"\\\\"),
+            byte => row.push(*byte)
+        }
+    }
+    row.extend_from_slice(format!("\t{}\n", file_type).as_bytes());
+
+    row
+}
+
+fn ls_command() -> Command {
+    Command::new()
+        .name("ls")
+        .usage("cli ls [path]")
+        .action(ls_action)
+        .flag(Flag::new("all", "cli ls [path] --all(-a)", FlagType::Bool).alias("a"))
+        .flag(Flag::new("no-all", "cli ls [path] --no-all", FlagType::Bool))
+        .flag(Flag::new("sort", "cli ls [path] --sort name|size|time", FlagType::String))
+        .flag(Flag::new("reverse", "cli ls [path] --reverse(-r)", FlagType::Bool).alias("r"))
+        .flag(format_usage("ls [path]"))
+        .flag(Flag::new("json", "cli ls [path] --json (same as --format json)", FlagType::Bool))
+}
+
+fn init_action(c: &Context) {
+    let script = match c.args.first().map(|s| s.as_str()) {
+        Some("bash") => include_str!("../../shell/lsk.bash"),
+        Some("zsh") => include_str!("../../shell/lsk.zsh"),
+        Some("fish") => include_str!("../../shell/lsk.fish"),
+        _ => {
+            eprintln!("Usage: lsk init bash|zsh|fish");
+            std::process::exit(1);
+        }
+    };
+
+    print!("{}", script);
+}
+
+fn init_command() -> Command {
+    Command::new()
+        .name("init")
+        .usage("cli init bash|zsh|fish")
+        .action(init_action)
+}
+
+fn load_config() -> Config {
+    Config::load().unwrap_or_else(|e| {
+        eprintln!("config: {}", e);
+        std::process::exit(2);
+    })
+}
+
+fn config_action(c: &Context) {
+    if c.bool_flag("print-effective") {
+        print!("{}", load_config().to_toml());
+    } else {
+        match config::config_path() {
+            Some(path) => println!("{}", path.display()),
+            None => {
+                eprintln!("no config path: neither XDG_CONFIG_HOME nor HOME is set");
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+fn config_command() -> Command {
+    Command::new()
+        .name("config")
+        .usage("cli config [--print-effective]")
+        .action(config_action)
+        .flag(Flag::new("print-effective", "cli config --print-effective", FlagType::Bool))
 }
 
 fn default_action(c: &Context) {
@@ -109,38 +407,93 @@ fn default_action(c: &Context) {
     };
 
     let test = false;
-    let mut all = false;
-    let mut fzf_path: Option<PathBuf> =  None;
-    let mut fzc_path: Option<PathBuf> = None;
-    let mut fzd_path: Option<PathBuf> = None;
-    let path = if path != "" {
+    let mut config = load_config();
+    let mut shell_output = ShellOutput::default();
+    config.sort = sort_flags(c, Format::Plain, config.sort);
+    let path = if let Some(name) = c.string_flag("bookmark") {
+        let bookmarks = Bookmarks::load().unwrap_or_else(|e| {
+            eprintln!("bookmarks: {}", e);
+            std::process::exit(2);
+        });
+        match bookmarks.get(&name) {
+            Some(dir) => dir.clone(),
+            None => {
+                eprintln!("no bookmark '{}'", name);
+                std::process::exit(2);
+            }
+        }
+    } else if !path.is_empty() {
         PathBuf::from(path)
     } else {
         env::current_dir().unwrap()
     };
 
-    if c.bool_flag("all") {
-        all = true;
+    config.all = all_flag(c, config.all);
+    if c.bool_flag("du") {
+        config.du = true;
+    }
+    if c.bool_flag("mouse") {
+        config.mouse = true;
+    }
+    match c.int_flag("level") {
+        Some(depth) if depth > 0 => config.tree = Some(depth as usize),
+        Some(depth) => {
+            eprintln!("--level: {} isn't a depth", depth);
+            std::process::exit(2);
+        },
+        None if c.bool_flag("tree") => config.tree = Some(config.tree.unwrap_or(1)),
+        None => ()
     }
     if let Some(path) = c.string_flag("fuzzy-find") {
-        fzf_path = Some(PathBuf::from(path));
+        config.hooks.fuzzy_find = Some(PathBuf::from(path));
     }
     if let Some(path) = c.string_flag("fuzzy-cmd") {
-        fzc_path = Some(PathBuf::from(path));
+        config.hooks.fuzzy_cmd = Some(PathBuf::from(path));
     }
     if let Some(path) = c.string_flag("fuzzy-dir") {
-        fzd_path = Some(PathBuf::from(path));
+        config.hooks.fuzzy_dir = Some(PathBuf::from(path));
+    }
+    if let Some(path) = c.string_flag("choosedir") {
+        shell_output.choosedir = Some(PathBuf::from(path));
+    }
+    if let Some(path) = c.string_flag("output") {
+        shell_output.output = Some(PathBuf::from(path));
+    }
+    if c.bool_flag("pick") {
+        shell_output.pick = true;
+    }
+
+    if shell_output.pick {
+        let mut stdout = terminal::tty::take_stdout().expect("pick fail: no terminal to draw on");
+        let separator = if c.bool_flag("print0") { b'\0' } else { b'\n' };
+        let ls_key = app::run(path, test, config, shell_output);
+        if ls_key.picked.is_empty() {
+            std::process::exit(1);
+        }
+        stdout.write_all(&separate_paths(&ls_key.picked, separator)).expect("failed to print picked paths");
+    } else {
+        app::run(path, test, config, shell_output);
     }
-    app::run(path, all, test, fzf_path, fzc_path, fzd_path);
 }
 
 #[cfg(test)]
 mod cli {
+    use std::ffi::OsStr;
     use std::fs::metadata;
+    use std::os::unix::ffi::OsStrExt;
     use std::path::Path;
     use super::ls_key::fixtures::{Fixture, command_assistors};
     use std::process::Command;
 
+    #[test]
+    fn ls_row_escapes_the_path() {
+        assert_eq!(super::plain_row(4, Path::new("/tmp/a file"), "file"), b"4\t/tmp/a file\tfile\n");
+        assert_eq!(super::plain_row(4, Path::new("lskt/tab\tname"), "file"), b"4\tlskt/tab\\tname\tfile\n");
+        assert_eq!(super::plain_row(5, Path::new("new\nline\\"), "dir"), b"5\tnew\\nline\\\\\tdir\n");
+        let raw = Path::new(OsStr::from_bytes(b"caf\xe9"));
+        assert_eq!(super::plain_row(6, raw, "file"), b"6\tcaf\xe9\tfile\n");
+    }
+
     #[test]
     #[ignore]//docker
     fn print_list_include_hidden() {
@@ -162,7 +515,7 @@ mod cli {
         let mut path_cache = command_assistors::PathCache::new(&path_path);
 
         // Changing directories.
-        path_cache.switch();
+        path_cache.switch().unwrap();
         let _output = Command::new("/ls-key/target/debug/lsk")
             .arg("-a")
             .output()
@@ -175,7 +528,7 @@ mod cli {
         //
         //println!("results:\n {}", String::from_utf8_lossy(&output.stdout));
 
-        path_cache.switch_back();
+        path_cache.switch_back().unwrap();
 
         assert_eq!(true, metadata(path.to_string() + "a-dir").unwrap().is_dir());
         assert_eq!(true, metadata(path.to_string() + ".a-hidden-dir").unwrap().is_dir());
@@ -210,7 +563,7 @@ mod cli {
         let mut path_cache = command_assistors::PathCache::new(&path_path);
 
         // Changing directories.
-        path_cache.switch();
+        path_cache.switch().unwrap();
         let output = Command::new("/ls-key/target/debug/lsk")
             .arg("get-file")
             .arg("/tmp/lsk_tests/")
@@ -219,7 +572,7 @@ mod cli {
             .expect("failed to execute lsk process");
 
 
-        path_cache.switch_back();
+        path_cache.switch_back().unwrap();
 
         assert_eq!(
             String::from_utf8_lossy(&output.stdout),
@@ -250,7 +603,7 @@ mod cli {
         let mut path_cache = command_assistors::PathCache::new(&path_path);
 
         // Changing directories.
-        path_cache.switch();
+        path_cache.switch().unwrap();
 
         let output_1 = Command::new("/ls-key/target/debug/lsk")
             .arg("get-file")
@@ -270,7 +623,7 @@ mod cli {
             .output()
             .expect("failed to execute lsk process");
 
-        path_cache.switch_back();
+        path_cache.switch_back().unwrap();
 
         assert_eq!(
             String::from_utf8_lossy(&output_1.stdout),
@@ -290,6 +643,68 @@ mod cli {
         fixture.teardown(true);
     }
 
+    #[test]
+    #[ignore]//docker
+    fn ls_keyed_rows() {
+        let path = "/tmp/lsk_tests/";
+
+        let mut fixture = Fixture::new()
+            .add_dirpath(path.to_string())
+            .add_dirpath(path.to_string() + "a-dir")
+            .add_dirpath(path.to_string() + ".a-hidden-dir")
+            .add_file(path.to_string() + "a-file")
+            .add_file(path.to_string() + ".a-hidden-file")
+            .build();
+
+        let output = Command::new("/ls-key/target/debug/lsk")
+            .arg("ls")
+            .arg("/tmp/lsk_tests/")
+            .output()
+            .expect("failed to execute lsk process");
+
+        let output_ndjson = Command::new("/ls-key/target/debug/lsk")
+            .arg("ls")
+            .arg("/tmp/lsk_tests/")
+            .arg("--format")
+            .arg("ndjson")
+            .output()
+            .expect("failed to execute lsk process");
+
+        let output_missing = Command::new("/ls-key/target/debug/lsk")
+            .arg("get-file")
+            .arg("/tmp/lsk_tests/")
+            .arg("9")
+            .arg("--format")
+            .arg("json")
+            .output()
+            .expect("failed to execute lsk process");
+
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "1\t/tmp/lsk_tests/a-dir\tdir\n2\t/tmp/lsk_tests/a-file\tfile\n"
+        );
+
+        let records: Vec<serde_json::Value> = String::from_utf8_lossy(&output_ndjson.stdout)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["key"], 1);
+        assert_eq!(records[0]["path"], "/tmp/lsk_tests/a-dir");
+        assert_eq!(records[0]["type"], "dir");
+        assert_eq!(records[1]["type"], "file");
+        assert_eq!(records[1]["size"], 0);
+        assert!(records[1]["mtime"].is_u64());
+
+        let missing: serde_json::Value = serde_json::from_slice(&output_missing.stdout).unwrap();
+        assert_eq!(output_missing.status.code(), Some(1));
+        assert_eq!(missing["key"], 9);
+        assert_eq!(missing["error"], "no entry for key 9");
+
+        fixture.teardown(true);
+    }
+
     #[test]
     #[ignore]//docker
     fn print_list_skip_hidden() {
@@ -311,9 +726,9 @@ mod cli {
         let mut path_cache = command_assistors::PathCache::new(&path_path);
 
         // Changing directories.
-        path_cache.switch();
+        path_cache.switch().unwrap();
 
-        path_cache.switch_back();
+        path_cache.switch_back().unwrap();
 
         assert_eq!(true, metadata(path.to_string() + "a-dir").unwrap().is_dir());
         assert_eq!(true, metadata(path.to_string() + "a-dir").unwrap().is_dir());
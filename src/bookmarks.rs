@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::{Path, PathBuf};
+use super::config::data_dir;
+use super::error::{self, Error};
+
+/// Named dirs, kept as `name = "/some/dir"` lines in
+/// $XDG_DATA_HOME/lsk/bookmarks.toml (or ~/.local/share/lsk/bookmarks.toml).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Bookmarks {
+    pub marks: BTreeMap<String, PathBuf>,
+    path: Option<PathBuf>,
+}
+
+impl Bookmarks {
+    pub fn load() -> error::Result<Self> {
+        match data_dir() {
+            Some(dir) => Bookmarks::from_file(dir.join("bookmarks.toml")),
+            None => Ok(Bookmarks::default())
+        }
+    }
+
+    // A missing file is just no bookmarks yet.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> error::Result<Self> {
+        let path = path.as_ref();
+        let marks = if path.exists() {
+            let toml = read_to_string(path).map_err(Error::at(path))?;
+            toml::from_str(&toml)
+                .map_err(|e| Error::Store(path.to_path_buf(), e.to_string()))?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Bookmarks { marks, path: Some(path.to_path_buf()) })
+    }
+
+    pub fn save(&self) -> error::Result<()> {
+        let path = self.path.as_ref().ok_or(Error::NoDataDir("bookmarks"))?;
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir).map_err(Error::at(dir))?;
+        }
+        let toml = toml::to_string(&self.marks).map_err(|e| Error::Store(path.clone(), e.to_string()))?;
+
+        write(path, toml).map_err(Error::at(path))
+    }
+
+    /// Without a name, the dir's own name is used.
+    pub fn add<P: AsRef<Path>>(&mut self, name: Option<&str>, dir: P) -> error::Result<String> {
+        let dir = dir.as_ref();
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .ok_or_else(|| Error::BadArg(format!("name the bookmark for {}", dir.display())))?
+        };
+        // `bm add` and `bm rm` would shadow these.
+        if name == "add" || name == "rm" {
+            return Err(Error::BadArg(format!("'{}' can't be a bookmark name", name)));
+        }
+        self.marks.insert(name.clone(), dir.to_path_buf());
+
+        Ok(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.marks.remove(name).is_some()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PathBuf> {
+        self.marks.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_and_remove() {
+        let mut bookmarks = Bookmarks::default();
+
+        assert_eq!(bookmarks.add(None, "/home/me/src/lsk").unwrap(), "lsk");
+        assert_eq!(bookmarks.add(Some("etc"), "/etc").unwrap(), "etc");
+        assert!(bookmarks.add(Some("rm"), "/etc").is_err());
+        assert!(bookmarks.add(None, "/").is_err());
+        assert_eq!(bookmarks.get("lsk"), Some(&PathBuf::from("/home/me/src/lsk")));
+        assert!(bookmarks.remove("etc"));
+        assert!(!bookmarks.remove("etc"));
+        assert_eq!(bookmarks.marks.len(), 1);
+    }
+
+    #[test]
+    fn save_without_a_path() {
+        assert!(Bookmarks::default().save().is_err());
+    }
+}
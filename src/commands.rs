@@ -0,0 +1,407 @@
+use std::fmt;
+use std::path::Path;
+use super::keymap::Keymap;
+use super::{parse_pane_key, Input, LsKey};
+
+/// Runs an entered command: the line as parsed, and whether it keys the matches
+/// of a fuzzy search.
+pub type Handler = fn(&mut LsKey, Input, bool);
+/// Runs a keyed line with its key.
+pub type KeyHandler = fn(&mut LsKey, usize, bool);
+/// Runs a range with its ends, the end None for `7-`.
+pub type RangeHandler = fn(&mut LsKey, usize, Option<usize>);
+
+/// How a command is spelled on the prompt.
+#[derive(Debug, Clone, Copy)]
+pub enum Syntax {
+    /// One of these words first.
+    Word(&'static [&'static str]),
+    /// One of these after `c`, on its own.
+    Shell(&'static [&'static str]),
+    /// A key, e.g. `7`.
+    Key,
+    /// A key in the left or right pane, e.g. `b7`.
+    PaneKey,
+    /// A range of keys, e.g. `2-7`.
+    Range,
+    /// A key and on, e.g. `7-`.
+    From,
+    /// A key rather than a line.
+    Keystroke,
+}
+
+/// What follows a word.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Args {
+    None,
+    /// At least one key.
+    Keys,
+    /// Keys, or none at all.
+    MaybeKeys,
+    OptionalKey,
+    OptionalCount,
+    /// A tab number, then at least one key.
+    TabKeys,
+    /// At least one pane key.
+    PaneKeys,
+    /// Free text, at least a word of it.
+    Text,
+    OptionalText,
+}
+
+/// What the prompt turns into while it's typed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Prompt {
+    Fuzzy,
+    Shell,
+    Work,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Run {
+    /// Once the line's entered.
+    Entered(Handler),
+    /// Once a key is entered.
+    Keyed(KeyHandler),
+    /// Once a range is entered.
+    Ranged(RangeHandler),
+    /// By the prompt, as it's typed.
+    Typed(Prompt),
+    /// By the prompt, on the keystroke.
+    Keystroke,
+}
+
+/// One thing the prompt understands, as `?` lists it.
+#[derive(Debug, Clone, Copy)]
+pub struct Command {
+    pub syntax: Syntax,
+    pub args: Args,
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub run: Run,
+}
+
+const fn command(syntax: Syntax, args: Args, usage: &'static str, help: &'static str, run: Run) -> Command {
+    Command { syntax, args, usage, help, run }
+}
+
+use self::Args as A;
+use self::Run::{Entered, Keyed, Keystroke, Ranged, Typed};
+use self::Syntax as S;
+
+/// Everything the prompt takes, in the order `?` shows it.
+pub const COMMANDS: &[Command] = &[
+    command(S::Key, A::None, "<key>", "open the keyed file, or go into the keyed dir; 0 goes up",
+        Keyed(|ls_key, key, is_fuzzed| if ls_key.tree.is_some() {
+            ls_key.tree_key_mode(key, is_fuzzed)
+        } else {
+            ls_key.key_mode(ls_key.list.clone(), key, is_fuzzed)
+        })),
+    command(S::Word(&["o"]), A::Keys, "o <key> [...]", "open with a program of your choosing",
+        Entered(|ls_key, input, is_fuzzed| ls_key.open_file_by_key_mode(input, is_fuzzed))),
+    command(S::Word(&["r"]), A::MaybeKeys, "r [key ...]", "return the paths to your shell; a bare r returns the marked ones",
+        Entered(|ls_key, input, is_fuzzed| if input.args.is_some() {
+            ls_key.return_file_by_key_mode(input, is_fuzzed)
+        } else {
+            ls_key.return_marked_mode()
+        })),
+    command(S::Range, A::None, "<key>-<key>", "show just that range of keys",
+        Ranged(|ls_key, start, end| ls_key.filter_mode(start, end))),
+    command(S::From, A::None, "<key>-", "show the page with that key on it; 0- for the first",
+        Ranged(|ls_key, start, end| ls_key.filter_mode(start, end))),
+    command(S::Keystroke, A::None, "pgdn, space", "next page (space only at an empty prompt)", Keystroke),
+    command(S::Keystroke, A::None, "pgup", "previous page", Keystroke),
+    command(S::Word(&["s"]), A::Text, "s <query>", "fuzzy search, then key one of the matches", Typed(Prompt::Fuzzy)),
+    command(S::Word(&["c"]), A::Text, "c <cmd>, c !<cmd>", "run a command in this dir and show what it prints, or give it the terminal", Typed(Prompt::Shell)),
+    command(S::Shell(&["sh", "!"]), A::None, "c sh, c !", "a shell in this dir",
+        Entered(|ls_key, _, _| ls_key.report(LsKey::subshell))),
+    command(S::Shell(&["fzf"]), A::None, "c fzf", "the fuzzy_find hook: open the file it prints",
+        Entered(|ls_key, _, _| ls_key.report(LsKey::fuzzy_find))),
+    command(S::Shell(&["fzc"]), A::None, "c fzc", "the fuzzy_cmd hook: run the command it prints",
+        Entered(|ls_key, _, _| ls_key.report(LsKey::fuzzy_cmd))),
+    command(S::Shell(&["fzd"]), A::None, "c fzd", "the fuzzy_dir hook: go to the dir it prints",
+        Entered(|ls_key, _, _| ls_key.report(LsKey::fuzzy_dir))),
+    command(S::Word(&["-", "+"]), A::None, "- / +", "back / forward to where you were",
+        Entered(|ls_key, input, _| ls_key.history_mode(input))),
+    command(S::Word(&["tab"]), A::OptionalKey, "tab [key]", "open a tab here, or at the keyed dir",
+        Entered(|ls_key, input, is_fuzzed| ls_key.tab_mode(input, is_fuzzed))),
+    command(S::Word(&["gt"]), A::OptionalCount, "gt [n]", "go to tab n, or the next one",
+        Entered(|ls_key, input, is_fuzzed| ls_key.tab_mode(input, is_fuzzed))),
+    command(S::Word(&["tabc"]), A::None, "tabc", "close this tab",
+        Entered(|ls_key, input, is_fuzzed| ls_key.tab_mode(input, is_fuzzed))),
+    command(S::Word(&["cpt", "mvt"]), A::TabKeys, "cpt/mvt <n> <key> [...]", "copy/move into tab n's dir",
+        Entered(|ls_key, input, is_fuzzed| ls_key.tab_mode(input, is_fuzzed))),
+    command(S::Word(&["dual"]), A::OptionalKey, "dual [key]", "open or close the right pane",
+        Entered(|ls_key, input, is_fuzzed| ls_key.pane_mode(input, is_fuzzed))),
+    command(S::PaneKey, A::None, "b<key>, a<key>", "key the right or left pane",
+        Entered(|ls_key, input, is_fuzzed| ls_key.pane_mode(input, is_fuzzed))),
+    command(S::Word(&["cp", "mv"]), A::PaneKeys, "cp/mv <a|b key> [...]", "copy/move into the other pane's dir",
+        Entered(|ls_key, input, is_fuzzed| ls_key.pane_mode(input, is_fuzzed))),
+    command(S::Word(&["tree"]), A::OptionalCount, "tree [depth]", "switch between the grid and a tree",
+        Entered(|ls_key, input, _| ls_key.tree_mode(input))),
+    command(S::Word(&["du"]), A::None, "du", "show or hide dir sizes",
+        Entered(|ls_key, _, _| ls_key.du_mode())),
+    command(S::Word(&["bm"]), A::OptionalText, "bm [name], bm add [name], bm rm <name>",
+        "go to a bookmark or pick one, bookmark this dir, or drop a bookmark",
+        Entered(|ls_key, input, _| ls_key.bookmark_mode(input))),
+    command(S::Word(&["z", "zi"]), A::OptionalText, "z <query>, zi [query]", "jump to a frequent dir, or pick from the matches",
+        Entered(|ls_key, input, _| ls_key.frecency_mode(input))),
+    command(S::Word(&["w"]), A::None, "w", "quit into the dir on screen", Typed(Prompt::Work)),
+    // Not re-entering the fuzzy loop in app::run is all it takes.
+    command(S::Word(&["q"]), A::None, "q", "quit",
+        Entered(|ls_key, _, _| ls_key.is_fuzzed = false)),
+    command(S::Word(&["?", "help"]), A::None, "?, help", "this",
+        Entered(|ls_key, _, _| ls_key.help_mode())),
+];
+
+/// Why an entered line isn't a command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// Not a key and no command's word.
+    Unknown(String),
+    /// The command, and what it took for a key.
+    NotAKey(String, String),
+    NotANumber(String, String),
+    NotAPaneKey(String, String),
+    BadRange(String),
+    /// Too few or too many args for the usage.
+    Usage(&'static str),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Unknown(word) => write!(f, "'{}' isn't a command, ? lists them", word),
+            ParseError::NotAKey(cmd, arg) => write!(f, "{}: '{}' isn't a key", cmd, arg),
+            ParseError::NotANumber(cmd, arg) => write!(f, "{}: '{}' isn't a number", cmd, arg),
+            ParseError::NotAPaneKey(cmd, arg) => write!(f, "{}: '{}' isn't a pane key like a3 or b7", cmd, arg),
+            ParseError::BadRange(range) => write!(f, "'{}' isn't a range like 2-7, or 7- for the page with 7", range),
+            ParseError::Usage(usage) => write!(f, "usage: {}", usage),
+        }
+    }
+}
+
+/// What an entered line led with, once parse has made sense of it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lead {
+    Word,
+    Key(usize),
+    Range(usize, Option<usize>),
+}
+
+/// An entered line's command, and the key or range it led with.
+#[derive(Debug, Clone, Copy)]
+pub struct Parsed {
+    pub command: &'static Command,
+    pub lead: Lead,
+}
+
+impl Parsed {
+    /// Hand the line to its command, the key or range as parsed.
+    pub fn run(self, ls_key: &mut LsKey, line: String, is_fuzzed: bool) {
+        match (self.command.run, self.lead) {
+            (Run::Entered(run), _) => run(ls_key, Input::new().parse(line), is_fuzzed),
+            (Run::Keyed(run), Lead::Key(key)) => run(ls_key, key, is_fuzzed),
+            (Run::Ranged(run), Lead::Range(start, end)) => run(ls_key, start, end),
+            // The prompt ran these as they were typed.
+            _ => {}
+        }
+    }
+}
+
+/// The command an entered line calls for, its args checked against the
+/// command's grammar.
+pub fn parse(line: &str) -> Result<Parsed, ParseError> {
+    let mut words = line.split(' ');
+    let first = words.next().unwrap_or_default();
+    let args: Vec<&str> = words.filter(|word| !word.is_empty()).collect();
+
+    let (command, lead) = if !first.is_empty() && first.chars().all(|c| c.is_ascii_digit()) {
+        let key = first.parse::<usize>().map_err(|_| ParseError::NotAKey("key".to_string(), first.to_string()))?;
+        (find(|syntax| matches!(syntax, Syntax::Key)), Lead::Key(key))
+    } else if first.starts_with(|c: char| c.is_ascii_digit()) && first.contains('-') {
+        let (start, end) = range(first)?;
+        let open = end.is_none();
+        (find(|syntax| matches!(syntax, Syntax::From) == open && matches!(syntax, Syntax::Range | Syntax::From)), Lead::Range(start, end))
+    } else if parse_pane_key(first).is_some() {
+        (find(|syntax| matches!(syntax, Syntax::PaneKey)), Lead::Word)
+    } else {
+        (find(|syntax| matches!(syntax, Syntax::Word(words) if words.contains(&first))), Lead::Word)
+    };
+    let command = command.ok_or_else(|| ParseError::Unknown(first.to_string()))?;
+    command.check(first, &args)?;
+
+    Ok(Parsed { command, lead })
+}
+
+/// `2-7` as (2, Some(7)), `7-` as (7, None). Backwards is the same range,
+/// so `7-2` is (2, Some(7)).
+pub fn range(text: &str) -> Result<(usize, Option<usize>), ParseError> {
+    let bad = || ParseError::BadRange(text.to_string());
+    let (start, end) = text.split_once('-').ok_or_else(bad)?;
+    let start: usize = start.parse().map_err(|_| bad())?;
+    if end.is_empty() {
+        return Ok((start, None));
+    }
+    let end: usize = end.parse().map_err(|_| bad())?;
+
+    Ok((start.min(end), Some(start.max(end))))
+}
+
+/// What a typed prompt starting with word turns into, e.g. `s` a fuzzy search.
+pub fn prompt(word: &str) -> Option<Prompt> {
+    let command = find(|syntax| matches!(syntax, Syntax::Word(words) if words.contains(&word)))?;
+    match command.run {
+        Run::Typed(prompt) => Some(prompt),
+        _ => None
+    }
+}
+
+/// The built-in a `c` line stands for, when it's one and not for the shell.
+pub fn shell_builtin(line: &str) -> Option<Handler> {
+    let command = find(|syntax| matches!(syntax, Syntax::Shell(words) if words.contains(&line)))?;
+    match command.run {
+        Run::Entered(handler) => Some(handler),
+        _ => None
+    }
+}
+
+/// Whether word is a built-in's, e.g. `tab` or `?`.
+pub fn is_word(word: &str) -> bool {
+    find(|syntax| matches!(syntax, Syntax::Word(words) if words.contains(&word))).is_some()
+}
+
+/// Whether a line typed from c on could be a built-in, e.g. `t` for `tab`,
+/// `7` for a key and `b` for `b7`.
+pub fn leads(c: char) -> bool {
+    COMMANDS.iter().any(|command| match command.syntax {
+        Syntax::Word(words) => words.iter().any(|word| word.starts_with(c)),
+        Syntax::Key | Syntax::Range | Syntax::From => c.is_ascii_digit(),
+        Syntax::PaneKey => c == 'a' || c == 'b',
+        Syntax::Shell(_) | Syntax::Keystroke => false
+    })
+}
+
+fn find<F: Fn(&Syntax) -> bool>(syntax: F) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| syntax(&command.syntax))
+}
+
+impl Command {
+    fn check(&self, name: &str, args: &[&str]) -> Result<(), ParseError> {
+        let usage = || ParseError::Usage(self.usage);
+        let key = |arg: &&str| arg.parse::<usize>().map_err(|_| ParseError::NotAKey(name.to_string(), arg.to_string()));
+        let keys = |args: &[&str]| args.iter().try_for_each(|arg| key(arg).map(drop));
+
+        match (self.args, args) {
+            (Args::None, []) => Ok(()),
+            (Args::Keys, [_, ..]) | (Args::MaybeKeys, _) | (Args::OptionalKey, [] | [_]) => keys(args),
+            (Args::OptionalCount, []) => Ok(()),
+            (Args::OptionalCount, [n]) => n.parse::<usize>().map(drop).map_err(|_| ParseError::NotANumber(name.to_string(), n.to_string())),
+            (Args::TabKeys, [n, rest @ ..]) if !rest.is_empty() => {
+                n.parse::<usize>().map_err(|_| ParseError::NotANumber(name.to_string(), n.to_string()))?;
+                keys(rest)
+            },
+            (Args::PaneKeys, [_, ..]) => args.iter()
+                .try_for_each(|arg| parse_pane_key(arg).map(drop).ok_or_else(|| ParseError::NotAPaneKey(name.to_string(), arg.to_string()))),
+            (Args::Text, [_, ..]) | (Args::OptionalText, _) => Ok(()),
+            _ => Err(usage())
+        }
+    }
+}
+
+/// The `?` overlay: the commands, then the config's keys, aliases and hooks.
+pub fn help(keymap: &Keymap, hooks: &[(&str, Option<&Path>)]) -> Vec<String> {
+    let mut lines = vec!["Commands".to_string()];
+    lines.extend(two_columns(COMMANDS.iter().map(|command| (command.usage.to_string(), command.help.to_string()))));
+
+    let bindings = keymap.bindings();
+    if !bindings.is_empty() {
+        lines.push(String::new());
+        lines.push("Keys".to_string());
+        lines.extend(two_columns(bindings.into_iter().map(|(key, line)| (key, line.to_string()))));
+    }
+
+    let aliases = keymap.aliases();
+    if !aliases.is_empty() {
+        lines.push(String::new());
+        lines.push("Aliases".to_string());
+        lines.extend(two_columns(aliases.into_iter().map(|(name, line)| (name.to_string(), line.to_string()))));
+    }
+
+    lines.push(String::new());
+    lines.push("Hooks".to_string());
+    lines.extend(two_columns(hooks.iter().map(|(name, path)| {
+        let path = path.map_or("not set".to_string(), |path| path.display().to_string());
+        (name.to_string(), path)
+    })));
+
+    lines
+}
+
+// Left column padded to its widest entry.
+fn two_columns<I: Iterator<Item = (String, String)>>(rows: I) -> Vec<String> {
+    let rows: Vec<(String, String)> = rows.collect();
+    let width = rows.iter().map(|(left, _)| left.chars().count()).max().unwrap_or(0);
+
+    rows.into_iter().map(|(left, right)| format!("  {:width$}  {}", left, right, width = width)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn usage(line: &str) -> Result<&'static str, ParseError> {
+        parse(line).map(|parsed| parsed.command.usage)
+    }
+
+    #[test]
+    fn parse_finds_the_command() {
+        assert_eq!(usage("7"), Ok("<key>"));
+        assert_eq!(usage("o 1 2"), Ok("o <key> [...]"));
+        assert_eq!(usage("r"), Ok("r [key ...]"));
+        assert_eq!(usage("2-7"), Ok("<key>-<key>"));
+        assert_eq!(usage("7-"), Ok("<key>-"));
+        assert_eq!(parse("7-5").map(|parsed| parsed.lead), Ok(Lead::Range(5, Some(7))));
+        assert_eq!(parse("7-").map(|parsed| parsed.lead), Ok(Lead::Range(7, None)));
+        assert_eq!(parse("12").map(|parsed| parsed.lead), Ok(Lead::Key(12)));
+        assert_eq!(usage("b3"), Ok("b<key>, a<key>"));
+        assert_eq!(usage("mvt 2 4 5"), Ok("cpt/mvt <n> <key> [...]"));
+        assert_eq!(usage("cp a1 b2"), Ok("cp/mv <a|b key> [...]"));
+        assert_eq!(usage("help"), Ok("?, help"));
+        assert_eq!(prompt("s"), Some(Prompt::Fuzzy));
+        assert_eq!(prompt("o"), None);
+        assert!(shell_builtin("!").is_some());
+        assert!(shell_builtin("ls").is_none());
+    }
+
+    #[test]
+    fn parse_says_what_is_wrong() {
+        assert_eq!(usage("o abc"), Err(ParseError::NotAKey("o".to_string(), "abc".to_string())));
+        assert_eq!(usage("5-x"), Err(ParseError::BadRange("5-x".to_string())));
+        assert_eq!(usage("7-2-"), Err(ParseError::BadRange("7-2-".to_string())));
+        assert_eq!(usage("o"), Err(ParseError::Usage("o <key> [...]")));
+        assert_eq!(usage("du 3"), Err(ParseError::Usage("du")));
+        assert_eq!(usage("gt x"), Err(ParseError::NotANumber("gt".to_string(), "x".to_string())));
+        assert_eq!(usage("cpt 2"), Err(ParseError::Usage("cpt/mvt <n> <key> [...]")));
+        assert_eq!(usage("cp 3"), Err(ParseError::NotAPaneKey("cp".to_string(), "3".to_string())));
+        assert_eq!(usage("vim Cargo.toml"), Err(ParseError::Unknown("vim".to_string())));
+        assert_eq!(usage("99999999999999999999999"), Err(ParseError::NotAKey("key".to_string(), "99999999999999999999999".to_string())));
+        assert_eq!(ParseError::NotAKey("o".to_string(), "abc".to_string()).to_string(), "o: 'abc' isn't a key");
+    }
+
+    #[test]
+    fn help_lists_the_config_too() {
+        let keys: BTreeMap<String, String> = vec![("ctrl-u".to_string(), "0".to_string())].into_iter().collect();
+        let aliases: BTreeMap<String, String> = vec![("gs".to_string(), "c git status".to_string())].into_iter().collect();
+        let keymap = Keymap::new(&keys, &aliases).unwrap();
+        let help = help(&keymap, &[("fuzzy_find", Some(Path::new("/hooks/fzf.sh"))), ("fuzzy_cmd", None)]);
+
+        assert_eq!(help[0], "Commands");
+        assert_eq!(help.iter().filter(|line| line.starts_with("  ")).count(), COMMANDS.len() + 4);
+        assert!(help.iter().any(|line| line.starts_with("  ctrl-u") && line.ends_with(" 0")));
+        assert!(help.iter().any(|line| line.starts_with("  gs") && line.ends_with("c git status")));
+        assert!(help.iter().any(|line| line.ends_with("/hooks/fzf.sh")));
+        assert!(help.iter().any(|line| line.starts_with("  fuzzy_cmd") && line.ends_with("not set")));
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use super::list::{Colors, Sort};
+use super::opener::Rule;
+use super::keymap::Keymap;
+use super::status::{self, Template};
+use super::error::{self, Error};
+
+/// Settings from $XDG_CONFIG_HOME/lsk/config.toml (or ~/.config/lsk/config.toml).
+///
+/// ```toml
+/// all = false
+/// tree = 2 # start in the tree view, this many levels deep
+/// du = false # show each dir's disk usage
+/// mouse = false # click, scroll and right-click the grid
+/// editor = "vim"
+/// opener = "xdg-open"
+/// status = "{path}[  {branch}]  {files} files" # see status::Template, "" hides it
+///
+/// [sort]
+/// by = "name" # or "size", "time"
+/// reverse = false
+///
+/// [colors]
+/// file = "white" # a name, or 0-255
+/// dir = "blue"
+///
+/// [hooks]
+/// fuzzy_find = "~/.fzf.sh"
+/// fuzzy_cmd = "~/.fzc.sh"
+/// fuzzy_dir = "~/.fzd.sh"
+///
+/// [keys] # see keymap::Keymap
+/// ctrl-u = "0"
+///
+/// [aliases]
+/// gs = "c git status"
+///
+/// [[open]] # see opener::Rule
+/// mime = "image/*"
+/// cmd = "feh"
+/// ```
+///
+/// Anything left out falls back to the defaults. The env vars (EDITOR,
+/// LSK_FILE_OPENER) override the file, and the CLI flags override both.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub all: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tree: Option<usize>,
+    pub du: bool,
+    pub mouse: bool,
+    pub editor: String,
+    pub opener: String,
+    pub status: String,
+    pub sort: Sort,
+    pub colors: Colors,
+    pub hooks: Hooks,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub open: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fuzzy_find: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fuzzy_cmd: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fuzzy_dir: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            all: false,
+            tree: None,
+            du: false,
+            mouse: false,
+            editor: "nano".to_string(),
+            opener: "xdg-open".to_string(),
+            status: status::DEFAULT.to_string(),
+            sort: Sort::default(),
+            colors: Colors::default(),
+            hooks: Hooks::default(),
+            keys: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            open: vec![],
+        }
+    }
+}
+
+impl Config {
+    /// The config file, then the env vars, on top of the defaults.
+    pub fn load() -> error::Result<Self> {
+        let mut config = match config_path() {
+            Some(path) if path.exists() => Config::from_file(path)?,
+            _ => Config::default()
+        };
+        config.apply_env();
+
+        Ok(config)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> error::Result<Self> {
+        let path = path.as_ref();
+        let toml = read_to_string(path).map_err(Error::at(path))?;
+
+        Config::parse(&toml)
+            .map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn parse(toml: &str) -> error::Result<Self> {
+        let mut config: Config = toml::from_str(toml).map_err(|e| Error::Config(e.to_string()))?;
+        config.colors.validate().map_err(Error::Config)?;
+        for rule in config.open.iter() {
+            rule.validate().map_err(Error::Config)?;
+        }
+        config.keymap()?;
+        Template::parse(&config.status).map_err(Error::Config)?;
+        config.hooks.fuzzy_find = config.hooks.fuzzy_find.map(expand_home);
+        config.hooks.fuzzy_cmd = config.hooks.fuzzy_cmd.map(expand_home);
+        config.hooks.fuzzy_dir = config.hooks.fuzzy_dir.map(expand_home);
+
+        Ok(config)
+    }
+
+    pub fn apply_env(&mut self) {
+        if let Ok(editor) = env::var("EDITOR") {
+            self.editor = editor;
+        }
+        if let Ok(opener) = env::var("LSK_FILE_OPENER") {
+            self.opener = opener;
+        }
+    }
+
+    pub fn keymap(&self) -> error::Result<Keymap> {
+        Keymap::new(&self.keys, &self.aliases).map_err(Error::Config)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).unwrap()
+    }
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    let config_home = match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".config")
+    };
+
+    Some(config_home.join("lsk").join("config.toml"))
+}
+
+// Where lsk keeps what it learns, e.g. bookmarks.
+pub fn data_dir() -> Option<PathBuf> {
+    let data_home = match env::var("XDG_DATA_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".local").join("share")
+    };
+
+    Some(data_home.join("lsk"))
+}
+
+fn expand_home(path: PathBuf) -> PathBuf {
+    match (path.strip_prefix("~"), env::var("HOME")) {
+        (Ok(rest), Ok(home)) => PathBuf::from(home).join(rest),
+        _ => path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::list::SortBy;
+
+    #[test]
+    fn parse_partial_config() {
+        let config = Config::parse(r#"
+            all = true
+
+            [sort]
+            by = "time"
+
+            [colors]
+            dir = "33"
+        "#).unwrap();
+
+        assert!(config.all);
+        assert_eq!(config.sort.by, SortBy::Time);
+        assert!(!config.sort.reverse);
+        assert_eq!(config.colors.dir, "33");
+        assert_eq!(config.colors.file, "white");
+        assert_eq!(config.editor, "nano");
+        assert_eq!(config.hooks, Hooks::default());
+    }
+
+    #[test]
+    fn parse_bad_config() {
+        assert!(Config::parse("sort = { by = \"colour\" }").is_err());
+        assert!(Config::parse("[colors]\nfile = \"plaid\"").is_err());
+        assert!(Config::parse("editr = \"vim\"").is_err());
+        assert!(Config::parse("[[open]]\ncmd = \"feh\"").is_err());
+        assert!(Config::parse("[aliases]\n3 = \"c ls\"").is_err());
+        assert!(Config::parse("status = \"{path} {colour}\"").is_err());
+    }
+
+    #[test]
+    fn effective_config_round_trips() {
+        let mut config = Config::default();
+        config.hooks.fuzzy_dir = Some(PathBuf::from("/home/me/.fzd.sh"));
+        config.keys.insert("ctrl-u".to_string(), "0".to_string());
+        config.open.push(Rule { mime: Some("image/*".to_string()), cmd: "feh".to_string(), ..Default::default() });
+
+        assert_eq!(Config::parse(&config.to_toml()).unwrap(), config);
+    }
+}
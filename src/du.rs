@@ -0,0 +1,125 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::symlink_metadata;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+#[derive(Debug, Default)]
+struct State {
+    // Size in bytes, and the dir's mtime when it was measured.
+    sizes: HashMap<PathBuf, (Option<SystemTime>, u64)>,
+    queue: VecDeque<PathBuf>,
+    working: bool,
+    done: usize,
+}
+
+/// Recursive dir sizes, like `du -s`, measured on a background thread.
+/// Clones share one cache, so every list and tab sees the same sizes.
+#[derive(Debug, Clone, Default)]
+pub struct Du {
+    state: Arc<Mutex<State>>,
+}
+
+// The same cache, not merely the same sizes.
+impl PartialEq for Du {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl Du {
+    /// The last size measured, even if the dir has changed since.
+    pub fn size(&self, dir: &Path) -> Option<u64> {
+        self.state.lock().unwrap().sizes.get(dir).map(|(_, size)| *size)
+    }
+
+    /// How many sizes have come in, so callers can tell when to redraw.
+    pub fn done(&self) -> usize {
+        self.state.lock().unwrap().done
+    }
+
+    /// Measure dirs that are new or whose mtime moved on. Replaces what's
+    /// still queued, so dirs no longer on screen are dropped.
+    pub fn request(&self, dirs: Vec<PathBuf>) {
+        let mut state = self.state.lock().unwrap();
+        state.queue = dirs
+            .into_iter()
+            .filter(|dir| match state.sizes.get(dir) {
+                Some((mtime, _)) => *mtime != modified(dir),
+                None => true
+            })
+            .collect();
+        if state.queue.is_empty() || state.working {
+            return;
+        }
+        state.working = true;
+
+        let shared = self.state.clone();
+        thread::spawn(move || loop {
+            let dir = {
+                let mut state = shared.lock().unwrap();
+                match state.queue.pop_front() {
+                    Some(dir) => dir,
+                    None => {
+                        state.working = false;
+                        return;
+                    }
+                }
+            };
+            let mtime = modified(&dir);
+            let size = disk_usage(&dir);
+            let mut state = shared.lock().unwrap();
+            state.sizes.insert(dir, (mtime, size));
+            state.done += 1;
+        });
+    }
+}
+
+fn modified(dir: &Path) -> Option<SystemTime> {
+    symlink_metadata(dir).and_then(|md| md.modified()).ok()
+}
+
+// Blocks actually used, as du counts them. Unreadable entries count as 0.
+pub fn disk_usage(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|md| md.blocks() * 512)
+        .sum()
+}
+
+/// 1024-based like `du -h`: 512, 4.0K, 1.2M, 30G.
+pub fn human(bytes: u64) -> String {
+    let units = ["K", "M", "G", "T", "P"];
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if size < 10.0 {
+        format!("{:.1}{}", size, units[unit])
+    } else {
+        format!("{:.0}{}", size, units[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn human_sizes() {
+        assert_eq!(human(512), "512");
+        assert_eq!(human(4096), "4.0K");
+        assert_eq!(human(1258291), "1.2M");
+        assert_eq!(human(30 * 1024 * 1024 * 1024), "30G");
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use super::commands::ParseError;
+
+/// What went wrong, worded for the status line. Nothing here is worth
+/// quitting over; lsk shows it and reads the next line.
+#[derive(Debug)]
+pub enum Error {
+    /// Nothing on screen has the key.
+    NoKey(usize),
+    /// A file or dir that couldn't be read, entered or found.
+    Io(PathBuf, io::Error),
+    /// A program that wouldn't start, e.g. the editor, or that failed.
+    Spawn(String, io::Error),
+    /// A hook the command needs that the config doesn't set, by its config name.
+    NoHook(&'static str),
+    Parse(ParseError),
+    /// A config file that doesn't parse, or a setting in it lsk can't use.
+    Config(String),
+    /// bookmarks.toml or frecency.toml, unreadable as TOML or unwritable as it.
+    Store(PathBuf, String),
+    /// What lsk can't keep, e.g. "bookmarks", with no XDG_DATA_HOME or HOME to keep it in.
+    NoDataDir(&'static str),
+    /// An arg the command couldn't use, e.g. "'x' isn't a tab".
+    BadArg(String),
+    /// What the command named isn't there, e.g. "no bookmark 'src'".
+    Missing(String),
+    /// A command's failure, led by the command.
+    Cmd(String, Box<Error>),
+    Other(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// For map_err on anything done to path.
+    pub fn at<P: AsRef<Path>>(path: P) -> impl FnOnce(io::Error) -> Error {
+        let path = path.as_ref().to_path_buf();
+        move |e| Error::Io(path, e)
+    }
+
+    /// For map_err on what cmd did, so the status line says which command failed.
+    pub fn cmd<S: AsRef<str>>(cmd: S) -> impl FnOnce(Error) -> Error {
+        let cmd = cmd.as_ref().to_string();
+        move |e| Error::Cmd(cmd, Box::new(e))
+    }
+
+    /// For map_err on starting program.
+    pub fn spawn<S: AsRef<str>>(program: S) -> impl FnOnce(io::Error) -> Error {
+        let program = program.as_ref().to_string();
+        move |e| Error::Spawn(program, e)
+    }
+}
+
+// The io::Error wording without the "(os error 13)".
+fn reason(e: &io::Error) -> String {
+    match e.kind() {
+        io::ErrorKind::PermissionDenied => "permission denied".to_string(),
+        io::ErrorKind::NotFound => "not found".to_string(),
+        _ => e.to_string()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NoKey(key) => write!(f, "no entry for key {}", key),
+            Error::Io(path, e) => write!(f, "{}: {}", path.display(), reason(e)),
+            Error::Spawn(program, e) => match e.kind() {
+                io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied => write!(f, "couldn't run {}: {}", program, reason(e)),
+                // It ran and failed; the wording already names it, e.g. "false exit with 1".
+                _ => write!(f, "{}", e)
+            },
+            Error::NoHook(name) => write!(f, "no {} hook set, see [hooks] in the config", name),
+            Error::Parse(e) => e.fmt(f),
+            Error::Config(message) | Error::BadArg(message) | Error::Missing(message) => f.write_str(message),
+            Error::Store(path, e) => write!(f, "{}: {}", path.display(), e),
+            Error::NoDataDir(what) => write!(f, "nowhere to save {}: neither XDG_DATA_HOME nor HOME is set", what),
+            Error::Cmd(cmd, e) => write!(f, "{}: {}", cmd, e),
+            Error::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(_, e) | Error::Spawn(_, e) => Some(e),
+            Error::Cmd(_, e) => Some(e.as_ref()),
+            _ => None
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::Parse(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worded_for_the_status_line() {
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert_eq!(Error::at("/root")(denied).to_string(), "/root: permission denied");
+        let missing = io::Error::from(io::ErrorKind::NotFound);
+        assert_eq!(Error::spawn("vim")(missing).to_string(), "couldn't run vim: not found");
+        assert_eq!(Error::NoKey(7).to_string(), "no entry for key 7");
+        assert_eq!(Error::NoHook("fuzzy_find").to_string(), "no fuzzy_find hook set, see [hooks] in the config");
+        assert_eq!(Error::cmd("bm")(Error::Missing("no bookmark 'src'".to_string())).to_string(), "bm: no bookmark 'src'");
+        assert_eq!(Error::NoDataDir("bookmarks").to_string(), "nowhere to save bookmarks: neither XDG_DATA_HOME nor HOME is set");
+        assert_eq!(Error::from(ParseError::BadRange("5-x".to_string())).to_string(), ParseError::BadRange("5-x".to_string()).to_string());
+    }
+}
@@ -104,6 +104,7 @@ impl Fixture {
 /// Switch back and forth between paths when executing test commands.
 pub mod command_assistors {
     use std::env;
+    use std::io;
     use std::path::Path;
 
     pub struct PathCache<'s> {
@@ -119,16 +120,13 @@ pub mod command_assistors {
             PathCache { from_path, to_path }
         }
 
-        pub fn switch(&mut self) {
-            if env::set_current_dir(&self.to_path).is_err() {
-                panic!("failed to switch back to original dir")
-            }
+        // E.g. into a dir without the permission to.
+        pub fn switch(&mut self) -> io::Result<()> {
+            env::set_current_dir(self.to_path)
         }
 
-        pub fn switch_back(&mut self) {
-            if env::set_current_dir(&self.from_path).is_err() {
-                panic!("failed to switch back to original dir")
-            }
+        pub fn switch_back(&mut self) -> io::Result<()> {
+            env::set_current_dir(&self.from_path)
         }
     }
 }
@@ -0,0 +1,184 @@
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use super::config::data_dir;
+use super::error::{self, Error};
+use super::list::fuzzy_score;
+
+// Once the ranks add up to this, they're all aged.
+const MAX_RANK: f64 = 10000.0;
+const AGING: f64 = 0.9;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dir {
+    pub path: PathBuf,
+    pub rank: f64,
+    pub last: u64,
+}
+
+impl Dir {
+    // Like zoxide: recent visits count for more.
+    pub fn score(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last);
+        let recency = if age < HOUR {
+            4.0
+        } else if age < DAY {
+            2.0
+        } else if age < WEEK {
+            0.5
+        } else {
+            0.25
+        };
+
+        self.rank * recency
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+struct Db {
+    #[serde(default)]
+    dir: Vec<Dir>,
+}
+
+/// Every dir lsk enters, ranked by how often and how lately, kept in
+/// $XDG_DATA_HOME/lsk/frecency.toml (or ~/.local/share/lsk/frecency.toml).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Frecency {
+    pub dirs: Vec<Dir>,
+    path: Option<PathBuf>,
+}
+
+impl Frecency {
+    pub fn load() -> error::Result<Self> {
+        match data_dir() {
+            Some(dir) => Frecency::from_file(dir.join("frecency.toml")),
+            None => Ok(Frecency::default())
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> error::Result<Self> {
+        let path = path.as_ref();
+        let db: Db = if path.exists() {
+            let toml = read_to_string(path).map_err(Error::at(path))?;
+            toml::from_str(&toml)
+                .map_err(|e| Error::Store(path.to_path_buf(), e.to_string()))?
+        } else {
+            Db::default()
+        };
+
+        Ok(Frecency { dirs: db.dir, path: Some(path.to_path_buf()) })
+    }
+
+    pub fn save(&self) -> error::Result<()> {
+        let path = self.path.as_ref().ok_or(Error::NoDataDir("the dir history"))?;
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir).map_err(Error::at(dir))?;
+        }
+        let db = Db { dir: self.dirs.clone() };
+        let toml = toml::to_string(&db).map_err(|e| Error::Store(path.clone(), e.to_string()))?;
+
+        write(path, toml).map_err(Error::at(path))
+    }
+
+    pub fn visit<P: AsRef<Path>>(&mut self, dir: P, now: u64) {
+        let dir = dir.as_ref();
+        match self.dirs.iter_mut().find(|d| d.path == dir) {
+            Some(d) => {
+                d.rank += 1.0;
+                d.last = now;
+            },
+            None => self.dirs.push(Dir { path: dir.to_path_buf(), rank: 1.0, last: now })
+        }
+
+        self.age();
+    }
+
+    // Old favourites fade so new ones can take over.
+    fn age(&mut self) {
+        let total: f64 = self.dirs.iter().map(|d| d.rank).sum();
+        if total > MAX_RANK {
+            for d in self.dirs.iter_mut() {
+                d.rank *= AGING;
+            }
+            self.dirs.retain(|d| d.rank >= 1.0);
+        }
+    }
+
+    /// Forget dirs that are gone. True if any were.
+    pub fn prune(&mut self) -> bool {
+        let before = self.dirs.len();
+        self.dirs.retain(|d| d.path.is_dir());
+
+        self.dirs.len() != before
+    }
+
+    /// Dirs whose path fuzzy matches query, best first. An empty query
+    /// matches everything.
+    pub fn query(&self, query: &str, now: u64) -> Vec<&Dir> {
+        let mut hits: Vec<(&Dir, i64)> = self.dirs
+            .iter()
+            .filter_map(|d| {
+                if query.is_empty() {
+                    return Some((d, 0));
+                }
+                fuzzy_score::score(&d.path.to_string_lossy(), query).map(|(score, _)| (d, score))
+            })
+            .collect();
+        hits.sort_by(|(a, a_match), (b, b_match)| {
+            b.score(now)
+                .partial_cmp(&a.score(now))
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b_match.cmp(a_match))
+        });
+
+        hits.into_iter().map(|(d, _)| d).collect()
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rank_by_frequency_and_recency() {
+        let mut frecency = Frecency::default();
+        let now = 10 * WEEK;
+        frecency.visit("/home/me/src/lsk", now - 2 * WEEK);
+        frecency.visit("/home/me/src/lsk", now - 2 * WEEK);
+        frecency.visit("/home/me/src/lsk", now - 2 * WEEK);
+        frecency.visit("/home/me/src/lisp", now - HOUR / 2);
+        frecency.visit("/etc", now);
+
+        let hits: Vec<&Path> = frecency.query("lsk", now).iter().map(|d| d.path.as_path()).collect();
+        assert_eq!(hits, vec![Path::new("/home/me/src/lsk")]);
+
+        // Three old visits lose to one fresh one.
+        let hits: Vec<&Path> = frecency.query("src", now).iter().map(|d| d.path.as_path()).collect();
+        assert_eq!(hits, vec![Path::new("/home/me/src/lisp"), Path::new("/home/me/src/lsk")]);
+
+        assert_eq!(frecency.query("", now).len(), 3);
+    }
+
+    #[test]
+    fn aging() {
+        let mut frecency = Frecency::default();
+        frecency.dirs.push(Dir { path: PathBuf::from("/big"), rank: MAX_RANK, last: 0 });
+        frecency.dirs.push(Dir { path: PathBuf::from("/small"), rank: 1.0, last: 0 });
+        frecency.visit("/new", 0);
+
+        assert_eq!(frecency.dirs.len(), 1);
+        assert_eq!(frecency.dirs[0].rank, MAX_RANK * AGING);
+    }
+}
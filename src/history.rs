@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+/// A dir as it was being looked at: its page of keys and fuzzy query.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Location {
+    pub dir: PathBuf,
+    pub filter: Option<Vec<usize>>,
+    pub page: usize,
+    pub query: Option<String>,
+}
+
+/// Browser-style back and forward. Going somewhere new drops the forward
+/// stack; going back or forward moves the current location across.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct History {
+    back: Vec<Location>,
+    forward: Vec<Location>,
+}
+
+impl History {
+    pub fn push(&mut self, from: Location) {
+        self.back.push(from);
+        self.forward.clear();
+    }
+
+    pub fn back(&mut self, here: Location) -> Option<Location> {
+        let to = self.back.pop()?;
+        self.forward.push(here);
+
+        Some(to)
+    }
+
+    pub fn forward(&mut self, here: Location) -> Option<Location> {
+        let to = self.forward.pop()?;
+        self.back.push(here);
+
+        Some(to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(dir: &str) -> Location {
+        Location { dir: PathBuf::from(dir), ..Default::default() }
+    }
+
+    #[test]
+    fn back_and_forward() {
+        let mut history = History::default();
+        assert_eq!(history.back(at("/a")), None);
+
+        history.push(at("/a"));
+        history.push(at("/b"));
+        assert_eq!(history.back(at("/c")), Some(at("/b")));
+        assert_eq!(history.back(at("/b")), Some(at("/a")));
+        assert_eq!(history.back(at("/a")), None);
+        assert_eq!(history.forward(at("/a")), Some(at("/b")));
+
+        // Somewhere new, so there's nothing to go forward to.
+        history.push(at("/b"));
+        assert_eq!(history.forward(at("/d")), None);
+        assert_eq!(history.back(at("/d")), Some(at("/b")));
+    }
+}
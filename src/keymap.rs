@@ -0,0 +1,241 @@
+use std::collections::{BTreeMap, HashMap};
+use termion::event::Key;
+use super::commands;
+
+/// Keystrokes and aliases from the config, e.g.
+///
+/// ```toml
+/// [keys]
+/// ctrl-u = "0"
+/// f2 = "c sh"
+/// alt-s = "c git status"
+///
+/// [aliases]
+/// gs = "c git status"
+/// ```
+///
+/// A binding is a prompt line that runs as soon as the key is pressed. An
+/// alias replaces the first word of a line when it's entered. Neither may
+/// take over a built-in: no alias is named after one or looks like a key,
+/// and no plain character is bound if a built-in starts with it, as it
+/// couldn't be typed at an empty prompt any more.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Keymap {
+    keys: HashMap<Key, String>,
+    aliases: HashMap<String, String>,
+}
+
+impl Keymap {
+    pub fn new(keys: &BTreeMap<String, String>, aliases: &BTreeMap<String, String>) -> Result<Self, String> {
+        let mut keymap = Keymap::default();
+
+        for (spec, line) in keys {
+            let key = parse_key(spec)?;
+            if let Key::Char(c) = key {
+                if commands::leads(c) {
+                    return Err(format!("key '{}' conflicts with a built-in command, see ?", spec));
+                }
+            }
+            if line.trim().is_empty() {
+                return Err(format!("key '{}' is bound to nothing", spec));
+            }
+            if keymap.keys.insert(key, line.clone()).is_some() {
+                return Err(format!("key '{}' is bound twice", spec));
+            }
+        }
+
+        for (name, line) in aliases {
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(format!("alias '{}' must be a single word", name));
+            }
+            if is_key_grammar(name) || commands::is_word(name) {
+                return Err(format!("alias '{}' conflicts with a built-in command, see ?", name));
+            }
+            if line.trim().is_empty() {
+                return Err(format!("alias '{}' expands to nothing", name));
+            }
+            keymap.aliases.insert(name.clone(), line.clone());
+        }
+
+        Ok(keymap)
+    }
+
+    /// The line bound to key. Plain characters only count at an empty
+    /// prompt, so they can still be typed everywhere else.
+    pub fn binding(&self, key: Key, prompt_empty: bool) -> Option<&str> {
+        match key {
+            Key::Char(_) if !prompt_empty => None,
+            _ => self.keys.get(&key).map(|line| line.as_str())
+        }
+    }
+
+    /// Each bound key by name, with its line, in name order.
+    pub fn bindings(&self) -> Vec<(String, &str)> {
+        let mut bindings: Vec<(String, &str)> = self.keys.iter().map(|(key, line)| (key_name(*key), line.as_str())).collect();
+        bindings.sort();
+
+        bindings
+    }
+
+    /// Each alias with its expansion, in name order.
+    pub fn aliases(&self) -> Vec<(&str, &str)> {
+        let mut aliases: Vec<(&str, &str)> = self.aliases.iter().map(|(name, line)| (name.as_str(), line.as_str())).collect();
+        aliases.sort();
+
+        aliases
+    }
+
+    /// The line with an alias in its first word expanded, if it has one.
+    pub fn expand(&self, line: &str) -> Option<String> {
+        let mut words = line.splitn(2, ' ');
+        let first = words.next()?;
+        let expansion = self.aliases.get(first)?;
+
+        match words.next() {
+            Some(rest) => Some(format!("{} {}", expansion, rest)),
+            None => Some(expansion.clone())
+        }
+    }
+}
+
+// Keys (`3`), ranges (`2-5`, `7-`), anything else made of digits and
+// dashes, and pane keys (`b3`).
+fn is_key_grammar(word: &str) -> bool {
+    word.chars().all(|c| c.is_ascii_digit() || c == '-') || super::parse_pane_key(word).is_some()
+}
+
+/// `ctrl-x`, `alt-x`, `f1`-`f12`, arrow and paging keys by name, or a
+/// single character.
+pub fn parse_key(spec: &str) -> Result<Key, String> {
+    let mut chars = spec.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(Key::Char(c));
+    }
+
+    let lower = spec.to_lowercase();
+    let single = |rest: &str| {
+        let mut chars = rest.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(format!("unknown key '{}'", spec))
+        }
+    };
+
+    if let Some(rest) = lower.strip_prefix("ctrl-") {
+        return single(rest).map(Key::Ctrl);
+    }
+    // Alt keeps the case, alt-S and alt-s are different keys.
+    if lower.starts_with("alt-") {
+        return single(&spec[4..]).map(Key::Alt);
+    }
+    if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+        if (1..=12).contains(&n) {
+            return Ok(Key::F(n));
+        }
+    }
+
+    match lower.as_str() {
+        "left" => Ok(Key::Left),
+        "right" => Ok(Key::Right),
+        "up" => Ok(Key::Up),
+        "down" => Ok(Key::Down),
+        "home" => Ok(Key::Home),
+        "end" => Ok(Key::End),
+        "pageup" => Ok(Key::PageUp),
+        "pagedown" => Ok(Key::PageDown),
+        "insert" => Ok(Key::Insert),
+        "delete" => Ok(Key::Delete),
+        "esc" => Ok(Key::Esc),
+        "tab" => Ok(Key::Char('\t')),
+        "backtab" => Ok(Key::BackTab),
+        _ => Err(format!("unknown key '{}'", spec))
+    }
+}
+
+/// The name parse_key knows key by.
+pub fn key_name(key: Key) -> String {
+    match key {
+        Key::Char('\t') => "tab".to_string(),
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("ctrl-{}", c),
+        Key::Alt(c) => format!("alt-{}", c),
+        Key::F(n) => format!("f{}", n),
+        Key::Left => "left".to_string(),
+        Key::Right => "right".to_string(),
+        Key::Up => "up".to_string(),
+        Key::Down => "down".to_string(),
+        Key::Home => "home".to_string(),
+        Key::End => "end".to_string(),
+        Key::PageUp => "pageup".to_string(),
+        Key::PageDown => "pagedown".to_string(),
+        Key::Insert => "insert".to_string(),
+        Key::Delete => "delete".to_string(),
+        Key::Esc => "esc".to_string(),
+        Key::BackTab => "backtab".to_string(),
+        key => format!("{:?}", key).to_lowercase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(parse_key("ctrl-u"), Ok(Key::Ctrl('u')));
+        assert_eq!(parse_key("Ctrl-U"), Ok(Key::Ctrl('u')));
+        assert_eq!(parse_key("alt-S"), Ok(Key::Alt('S')));
+        assert_eq!(parse_key("f2"), Ok(Key::F(2)));
+        assert_eq!(parse_key("PageDown"), Ok(Key::PageDown));
+        assert_eq!(parse_key("x"), Ok(Key::Char('x')));
+        assert!(parse_key("f13").is_err());
+        assert!(parse_key("ctrl-up").is_err());
+
+        for spec in ["ctrl-u", "alt-S", "f2", "pagedown", "tab", "x"] {
+            assert_eq!(key_name(parse_key(spec).unwrap()), spec);
+        }
+    }
+
+    #[test]
+    fn bindings_and_aliases() {
+        let keymap = Keymap::new(
+            &map(&[("ctrl-u", "0"), ("x", "c sh")]),
+            &map(&[("gs", "c git status")])
+        ).unwrap();
+
+        assert_eq!(keymap.binding(Key::Ctrl('u'), false), Some("0"));
+        assert_eq!(keymap.binding(Key::Char('x'), true), Some("c sh"));
+        assert_eq!(keymap.binding(Key::Char('x'), false), None);
+        assert_eq!(keymap.expand("gs"), Some("c git status".to_string()));
+        assert_eq!(keymap.expand("gs --short"), Some("c git status --short".to_string()));
+        assert_eq!(keymap.expand("g"), None);
+    }
+
+    #[test]
+    fn conflicts() {
+        let none = BTreeMap::new();
+
+        assert!(Keymap::new(&map(&[("3", "c ls")]), &none).is_err());
+        assert!(Keymap::new(&map(&[("s", "c ls")]), &none).is_err());
+        assert!(Keymap::new(&map(&[("ctrl-u", "0"), ("Ctrl-U", "1")]), &none).is_err());
+        assert!(Keymap::new(&map(&[("ctrl-u", " ")]), &none).is_err());
+        assert!(Keymap::new(&none, &map(&[("12", "c ls")])).is_err());
+        assert!(Keymap::new(&none, &map(&[("2-", "c ls")])).is_err());
+        assert!(Keymap::new(&none, &map(&[("b7", "c ls")])).is_err());
+        assert!(Keymap::new(&none, &map(&[("o", "c ls")])).is_err());
+        assert!(Keymap::new(&none, &map(&[("g s", "c ls")])).is_err());
+
+        // Built-ins added since count too, words and leading characters both.
+        for word in ["tab", "gt", "tabc", "z", "zi", "bm", "dual", "du", "?", "help", "+", "q"] {
+            assert!(Keymap::new(&none, &map(&[(word, "c ls")])).is_err(), "alias {}", word);
+        }
+        for key in ["b", "a", "t", "d", "g", "z", "m", "?", "+", "-"] {
+            assert!(Keymap::new(&map(&[(key, "c ls")]), &none).is_err(), "key {}", key);
+        }
+        assert!(Keymap::new(&map(&[("x", "c ls")]), &map(&[("tb", "tab")])).is_ok());
+    }
+}
@@ -1,8 +1,15 @@
 use std::path::{Path, PathBuf};
-use std::fs::metadata;
+use std::fs::{metadata, read_dir, Metadata};
 use std::borrow::Cow;
-use walkdir::{DirEntry, WalkDir, Error as WalkDirError};
+use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::str::FromStr;
+use std::fmt;
 use ansi_term::Colour;
+use serde::{Deserialize, Serialize};
+use super::du::{self, Du};
+use super::loader::Loader;
+use super::error::{self, Error};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum FileType {
@@ -87,6 +94,152 @@ mod test_entries_sort {
             vec![a, b, c]
         )
     }
+
+    #[test]
+    fn parse_sort_by() {
+        assert_eq!("name".parse(), Ok(SortBy::Name));
+        assert_eq!("size".parse(), Ok(SortBy::Size));
+        assert_eq!("time".parse(), Ok(SortBy::Time));
+        assert!("bogus".parse::<SortBy>().is_err());
+    }
+
+    #[test]
+    fn keys_stay_put_while_loading() {
+        let entry = |path: &str| Entry {
+            path: PathBuf::from(path),
+            file_type: FileType::File,
+            key: None,
+        };
+        let mut list = List::new("/nonexistent");
+        list.files = vec![entry("m"), entry("b")];
+        let (more, rest) = std::sync::mpsc::channel();
+        list.loader = Some(Loader::start(rest.into_iter()));
+
+        more.send(entry("a")).unwrap();
+        assert!(!list.catch_up());
+        assert_eq!(list.get_file_by_key(1, true).unwrap(), PathBuf::from("/nonexistent/b"));
+
+        // Read in full, a sorts in ahead of it.
+        drop(more);
+        while !list.catch_up() {
+            std::thread::yield_now();
+        }
+        assert_eq!(list.get_file_by_key(1, true).unwrap(), PathBuf::from("/nonexistent/a"));
+        assert_eq!(list.get_file_by_key(2, true).unwrap(), PathBuf::from("/nonexistent/b"));
+    }
+
+    #[test]
+    fn sort_entries_reverse() {
+        let entry = |path: &str| Entry {
+            path: PathBuf::from(path),
+            file_type: FileType::File,
+            key: None,
+        };
+        let mut list = List::new("/nonexistent");
+        list.sort.reverse = true;
+
+        let mut entries = vec![entry("b"), entry("C"), entry("a")];
+        list.sort_entries(&mut entries);
+
+        assert_eq!(
+            entries,
+            vec![entry("C"), entry("b"), entry("a")]
+        )
+    }
+}
+
+// Read up front by List::stream; plenty for a screenful.
+const FIRST_PAGE: usize = 1000;
+
+// Like ls, size and time put the largest and newest first.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    #[default]
+    Name,
+    Size,
+    Time,
+}
+
+impl FromStr for SortBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(SortBy::Name),
+            "size" => Ok(SortBy::Size),
+            "time" => Ok(SortBy::Time),
+            _ => Err(format!("unknown sort '{}', expected name, size or time", s))
+        }
+    }
+}
+
+impl fmt::Display for SortBy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            SortBy::Name => "name",
+            SortBy::Size => "size",
+            SortBy::Time => "time",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sort {
+    pub by: SortBy,
+    pub reverse: bool,
+}
+
+// Colour names, or 0-255 for the extended palette.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub file: String,
+    pub dir: String,
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Colors {
+            file: "white".to_string(),
+            dir: "blue".to_string(),
+        }
+    }
+}
+
+impl Colors {
+    pub fn validate(&self) -> Result<(), String> {
+        for name in [&self.file, &self.dir].iter() {
+            if colour(name).is_none() {
+                return Err(format!("unknown colour '{}'", name));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn file(&self) -> Colour {
+        colour(&self.file).unwrap_or(Colour::White)
+    }
+
+    pub fn dir(&self) -> Colour {
+        colour(&self.dir).unwrap_or(Colour::Blue)
+    }
+}
+
+pub fn colour(name: &str) -> Option<Colour> {
+    match name {
+        "black" => Some(Colour::Black),
+        "red" => Some(Colour::Red),
+        "green" => Some(Colour::Green),
+        "yellow" => Some(Colour::Yellow),
+        "blue" => Some(Colour::Blue),
+        "purple" | "magenta" => Some(Colour::Purple),
+        "cyan" => Some(Colour::Cyan),
+        "white" => Some(Colour::White),
+        _ => name.parse().ok().map(Colour::Fixed)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Default)]
@@ -94,7 +247,14 @@ pub struct List {
     pub files: Vec<Entry>,
     pub parent_path: PathBuf,
     pub path_history: Vec<PathBuf>,
-    pub filter: Option<Vec<usize>>
+    pub filter: Option<Vec<usize>>,
+    // The page on screen, counting from 0.
+    pub page: usize,
+    pub sort: Sort,
+    // Dir sizes, when they're being shown.
+    pub du: Option<Du>,
+    // The rest of a big dir, still being read.
+    pub loader: Option<Loader>,
 }
 
 impl List {
@@ -110,54 +270,67 @@ impl List {
     pub fn update<P: AsRef<Path>>(mut self, path: P) -> Self {
         let old_path_history = self.path_history;
         let old_parent_path = self.parent_path;
+        let old_sort = self.sort;
+        let old_du = self.du;
         let p = path.as_ref().to_str().unwrap();
         let np: String = basename(p, '/').into_owned();
         let basename = Path::new(&np);
         let list: List = Default::default();
         self = list;
         self.path_history = old_path_history;
+        self.sort = old_sort;
+        self.du = old_du;
         self.parent_path = old_parent_path.join(basename);
         self.path_history.push(self.parent_path.clone());
 
         self
     }
 
-    pub fn list_skip_hidden(mut self) -> Result<Self, std::io::Error> {
-        let list: List = Default::default();
-        let walker = WalkDir::new(&self.parent_path).max_depth(1).into_iter();
-        for entry in walker.filter_entry(|e| !list.clone().skip(e)) {
-                self = list_maker(entry, self)?;
-        }
+    pub fn list_skip_hidden(mut self) -> error::Result<Self> {
+        self.files.extend(read_entries(&self.parent_path, false)?);
 
         Ok(self)
     }
 
-    pub fn list_include_hidden(mut self) -> Result<Self, std::io::Error> {
-        let mut _list: List = Default::default();
-        for entry in WalkDir::new(&self.parent_path).max_depth(1) {
-                self = list_maker(entry, self)?;
-        }
+    pub fn list_include_hidden(mut self) -> error::Result<Self> {
+        self.files.extend(read_entries(&self.parent_path, true)?);
 
         Ok(self)
     }
 
-    fn replace_shortest_path(mut self, pathbuf: PathBuf) -> Self {
-        let path = pathbuf.into_boxed_path();
-        let depth_from_root_dir = path.iter().count();
-
-        let parent_dir_count = self.parent_path.iter().count();
-        if depth_from_root_dir < parent_dir_count {
-            self.parent_path = path.to_path_buf();
+    /// Like list_skip_hidden/list_include_hidden, but only the first page
+    /// is read here. The rest comes in on a worker thread; see catch_up.
+    pub fn stream(mut self, all: bool) -> error::Result<Self> {
+        let mut entries = read_entries(&self.parent_path, all)?.peekable();
+        self.files.extend(entries.by_ref().take(FIRST_PAGE));
+        if entries.peek().is_some() {
+            self.loader = Some(Loader::start(entries));
         }
 
-        self
+        Ok(self)
+    }
+
+    /// Add the rest of the dir, once the loader has read it all. Until then
+    /// the keys on screen stay put, as the rest would sort in among them.
+    /// True if the list changed.
+    pub fn catch_up(&mut self) -> bool {
+        match self.loader.take() {
+            Some(mut loader) if loader.read() => {
+                self.files.extend(loader.poll());
+                true
+            },
+            loader => {
+                self.loader = loader;
+                false
+            }
+        }
     }
 
     pub fn order_and_sort_list(&mut self, sort: bool, filter: bool) -> Vec<Entry> {
         let mut all_files = self.files.clone();
         let previous_path = self.path_history.iter().last().unwrap();
         if sort {
-            all_files.sort_by(|a, b| alphabetize_entry(a, b));
+            self.sort_entries(&mut all_files);
         }
         all_files.insert(
             0,
@@ -182,48 +355,72 @@ impl List {
 
         // Filter entries
         if filter {
+            final_all_files = filter_entries(final_all_files, &self.filter);
+        }
 
-           fn filter_thing(x: &Entry, filter: &Option<Vec<usize>>) -> bool {
-               if let Some(fl) = filter {
-                   if let Some(key) = x.key {
-                       fl.iter().find(|n| &key == *n).is_some()
-                   } else {
-                       true
-                   }
-               } else {
-                   true
-               }
-           }
-
-           let find_in_range = final_all_files.clone().into_iter().filter(|x|
-               // !filter.as_ref().unwrap().iter().find(|n| &x.key.unwrap() == *n).is_some()
-               filter_thing(x, &self.filter)
-           );
-
-            let files: Vec<Entry> = find_in_range.collect();
-
-            let entry_test = Entry {
-                path: PathBuf::from("entry_test"),
-                file_type: FileType::File,
-                key: Some(3)
+        final_all_files
+    }
 
-            };
+    // Alphabetical first, so entries that tie on size or time stay in name order.
+    pub fn sort_entries(&self, entries: &mut [Entry]) {
+        // Same order as alphabetize_entry, without its allocations per comparison.
+        entries.sort_by_cached_key(|entry| entry.path.to_string_lossy().to_lowercase());
+        match self.sort.by {
+            SortBy::Name => (),
+            SortBy::Size => entries.sort_by_cached_key(|entry|
+                Reverse(self.dir_size(entry).or_else(|| self.entry_metadata(entry).map(|md| md.len())).unwrap_or(0))
+            ),
+            SortBy::Time => entries.sort_by_cached_key(|entry|
+                Reverse(self.entry_metadata(entry).and_then(|md| md.modified().ok()))
+            ),
+        }
+        if self.sort.reverse {
+            entries.reverse();
+        }
+    }
 
-            if files != vec![entry_test] {
-                //let few_ms = std::time::Duration::from_millis(1000);
-                //std::thread::sleep(few_ms);
-            } else {
-                //let few_ms = std::time::Duration::from_millis(1000);
-                //std::thread::sleep(few_ms);
-            }
-            final_all_files = files;
-        } else {
+    fn entry_metadata(&self, entry: &Entry) -> Option<Metadata> {
+        metadata(self.parent_path.join(&entry.path)).ok()
+    }
+
+    // Once du has got to it.
+    pub fn dir_size(&self, entry: &Entry) -> Option<u64> {
+        match (&self.du, &entry.file_type) {
+            (Some(du), FileType::Dir) => du.size(&self.parent_path.join(&entry.path)),
+            _ => None
         }
+    }
 
-        final_all_files
+    /// Bytes in the files listed, and in the dirs du has got to.
+    pub fn total_size(&self) -> u64 {
+        self.files
+            .iter()
+            .filter_map(|entry| match entry.file_type {
+                FileType::File => self.entry_metadata(entry).map(|md| md.len()),
+                FileType::Dir => self.dir_size(entry)
+            })
+            .sum()
+    }
+
+    /// Dot entries here, listed or not.
+    pub fn hidden_count(&self) -> usize {
+        read_dir(&self.parent_path).map_or(0, |entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with('.'))
+                .count()
+        })
+    }
+
+    pub fn subdirs(&self) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .filter(|entry| entry.file_type == FileType::Dir)
+            .map(|entry| self.parent_path.join(&entry.path))
+            .collect()
     }
 
-    pub fn get_file_by_key(&self, key: usize, sort: bool) -> Option<PathBuf> {
+    pub fn get_file_by_key(&self, key: usize, sort: bool) -> error::Result<PathBuf> {
         let all_files = order_and_sort_list(&self, sort);
         let all_files = all_files.iter();
 
@@ -234,28 +431,16 @@ impl List {
             //let parent_file_name = file_or_dir_name(&self.parent_path);
             if n == key {
                 let path = entry.path.to_path_buf();
-                return self.clone().full_entry_path(path);
+                return Ok(self.clone().full_entry_path(path));
             }
         }
 
-        return None
-    }
-
-    // Caution ahead, side-effect: set parent dir field.
-    fn skip(self, entry: &DirEntry) -> bool {
-
-        //if is_parent_dir(entry) {
-        //    return true
-        //}
-        entry.file_name()
-             .to_str()
-             .map(|s| s.starts_with("."))
-             .unwrap_or(false)
+        Err(Error::NoKey(key))
     }
 
-    fn full_entry_path(self, path: PathBuf) -> Option<PathBuf> {
+    fn full_entry_path(self, path: PathBuf) -> PathBuf {
         let p = self.parent_path;
-        Some(p.join(path.as_path()))
+        p.join(path.as_path())
     }
 }
 
@@ -267,71 +452,54 @@ fn basename<'a>(path: &'a str, sep: char) -> Cow<'a, str> {
     }
 }
 
-fn file_or_dir_name(path: &PathBuf) -> Option<PathBuf> {
-    let path = path.as_path();
-    let path = path.file_name();
+/// Just the entries whose keys are in filter; all of them without one.
+pub fn filter_entries(entries: Vec<Entry>, filter: &Option<Vec<usize>>) -> Vec<Entry> {
+    let keys: HashSet<usize> = match filter {
+        Some(keys) => keys.iter().copied().collect(),
+        None => return entries
+    };
 
-    if let Some(p) = path {
-        Some(Path::new(&p).to_path_buf())
-    } else {
-        None
-    }
+    entries
+        .into_iter()
+        .filter(|entry| entry.key.is_none_or(|key| keys.contains(&key)))
+        .collect()
 }
 
-fn list_maker(entry: Result<DirEntry, WalkDirError>, mut list: List) -> Result<List, std::io::Error> {
-    match entry {
-        Ok(entry) => {
-            let entry = entry.path();
-            let parent_file_name = file_or_dir_name(&list.parent_path);
-
-                match metadata(entry) {
-                    Ok(md) => {
-                       let path = entry.to_path_buf();
-                       let short_path = file_or_dir_name(&path);
-                       if md.is_file() {
-                           list = list.replace_shortest_path(path);
-                           if let Some(p) = short_path {
-
-                               if Some(p.clone()) != parent_file_name {
-                                   list.files.push(
-                                       Entry {
-                                           path: p,
-                                           file_type: FileType::File,
-                                           key: None
-                                       }
-                                    );
-                               }
-                           }
-                       } else if md.is_dir() {
-                           list = list.replace_shortest_path(path);
-                           if let Some(p) = short_path {
-                               if Some(p.clone()) != parent_file_name {
-                                   list.files.push(
-                                       Entry {
-                                           path: p,
-                                           file_type: FileType::Dir,
-                                           key: None
-                                       }
-                                    );
-                               }
-                           }
-                       }
-                    },
-                    Err(_) => ()
-                }
-        },
-        Err(_) => ()
-    }
+// A dir's files and dirs, by name. Only symlinks are stat'ed, to see what
+// they point at; broken ones, sockets and the like are left out. Only a
+// dir that can't be read at all is an error.
+fn read_entries(dir: &Path, all: bool) -> error::Result<impl Iterator<Item = Entry> + Send + 'static> {
+    let dir = dir.to_path_buf();
+    let entries = read_dir(&dir).map_err(Error::at(&dir))?;
+
+    Ok(entries
+        .filter_map(|entry| entry.ok())
+        .filter(move |entry| all || !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(move |entry| {
+            let file_type = entry.file_type().ok()?;
+            let (is_file, is_dir) = if file_type.is_symlink() {
+                let md = metadata(dir.join(entry.file_name())).ok()?;
+                (md.is_file(), md.is_dir())
+            } else {
+                (file_type.is_file(), file_type.is_dir())
+            };
+            let file_type = match (is_file, is_dir) {
+                (true, _) => FileType::File,
+                (_, true) => FileType::Dir,
+                _ => return None
+            };
 
-    Ok(list)
+            Some(Entry { path: PathBuf::from(entry.file_name()), file_type, key: None })
+        }))
 }
 
+// Neither, if it's gone.
 pub fn is_file<P: AsRef<Path>>(path: P) -> bool {
-    metadata(path).unwrap().is_file()
+    metadata(path).is_ok_and(|md| md.is_file())
 }
 
 pub fn is_dir<P: AsRef<Path>>(path: P) -> bool {
-    metadata(path).unwrap().is_dir()
+    metadata(path).is_ok_and(|md| md.is_dir())
 }
 
 //pub fn go_back_compoenent_display() {
@@ -341,15 +509,21 @@ pub fn is_dir<P: AsRef<Path>>(path: P) -> bool {
 //    let last = component.as_os_str();
 //}
 
-pub fn key_entries(entries: Vec<Entry>) -> Vec<String> {
+pub fn key_entries(list: &List, entries: Vec<Entry>, colors: &Colors) -> Vec<String> {
+    pane_key_entries(list, entries, colors, "")
+}
+
+// Keys shown as [<prefix><key>], e.g. [b3] in the right pane. Dirs du has
+// measured get their size after the key.
+pub fn pane_key_entries(list: &List, entries: Vec<Entry>, colors: &Colors, prefix: &str) -> Vec<String> {
     let mut entries_keyed: Vec<String> = vec![];
     for entry in entries.clone() {
         let n = entry.key.unwrap();
         let entry = match entry.file_type {
             FileType::File => {
                 let entry = entry.path.to_str().unwrap();
-                let entry = format!(r#"{} [{}]"#, entry, n);
-                Colour::White.bold().paint(entry).to_string()
+                let entry = format!(r#"{} [{}{}]"#, entry, prefix, n);
+                colors.file().bold().paint(entry).to_string()
             },
             FileType::Dir => {
                 if n == 0 {
@@ -359,14 +533,19 @@ pub fn key_entries(entries: Vec<Entry>) -> Vec<String> {
                     let entry_str = os_str.to_str().unwrap();
                     if entry_str != "/" {
                           let entry_string = format!("../{}", entry_str);
-                          Colour::Blue.bold().paint(entry_string).to_string()
+                          colors.dir().bold().paint(entry_string).to_string()
                     } else {
                         "/".to_string()
                     }
                 } else {
+                    let size = list.dir_size(&entry);
                     let entry_str = entry.path.to_str().unwrap();
-                    let entry = format!(r#"{} [{}]"#, entry_str, n);
-                    Colour::Blue.bold().paint(entry).to_string()
+                    let entry = format!(r#"{} [{}{}]"#, entry_str, prefix, n);
+                    let entry = colors.dir().bold().paint(entry).to_string();
+                    match size {
+                        Some(size) => format!("{} {}", entry, du::human(size)),
+                        None => entry
+                    }
                 }
             },
         };
@@ -382,7 +561,7 @@ pub fn order_and_sort_list(list: &List, sort: bool) -> Vec<Entry> {
     let mut all_files = list.files.clone();
     let previous_path = list.path_history.iter().last().unwrap();
     if sort {
-        all_files.sort_by(|a, b| alphabetize_entry(a, b));
+        list.sort_entries(&mut all_files);
         //all_files = alphabetize_paths_vec(all_files.clone());
     }
     all_files.insert(
@@ -0,0 +1,111 @@
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use super::list::Entry;
+
+// Entries are handed over this many at a time.
+const BATCH: usize = 1024;
+
+#[derive(Debug, Default)]
+struct State {
+    entries: Vec<Entry>,
+    done: bool,
+}
+
+/// The rest of a dir, read on a worker thread. Each clone keeps its own
+/// place, so every copy of a list can catch up on its own. The worker
+/// gives up once the last copy is dropped.
+#[derive(Debug, Clone)]
+pub struct Loader {
+    state: Arc<Mutex<State>>,
+    seen: usize,
+}
+
+impl PartialEq for Loader {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.state, &other.state) && self.seen == other.seen
+    }
+}
+
+impl Loader {
+    pub fn start<I>(entries: I) -> Self
+        where I: Iterator<Item = Entry> + Send + 'static
+    {
+        let state = Arc::new(Mutex::new(State::default()));
+        let shared = Arc::downgrade(&state);
+        thread::spawn(move || {
+            let mut batch = vec![];
+            for entry in entries {
+                batch.push(entry);
+                if batch.len() == BATCH && !hand_over(&shared, &mut batch, false) {
+                    return;
+                }
+            }
+            hand_over(&shared, &mut batch, true);
+        });
+
+        Loader { state, seen: 0 }
+    }
+
+    /// What's been read since last time.
+    pub fn poll(&mut self) -> Vec<Entry> {
+        let state = self.state.lock().unwrap();
+        let entries = state.entries[self.seen..].to_vec();
+        self.seen = state.entries.len();
+
+        entries
+    }
+
+    /// The worker has read the whole dir, polled or not.
+    pub fn read(&self) -> bool {
+        self.state.lock().unwrap().done
+    }
+
+    /// The whole dir is read and polled.
+    pub fn done(&self) -> bool {
+        let state = self.state.lock().unwrap();
+
+        state.done && self.seen == state.entries.len()
+    }
+}
+
+// False once nobody wants the entries any more.
+fn hand_over(shared: &Weak<Mutex<State>>, batch: &mut Vec<Entry>, done: bool) -> bool {
+    match shared.upgrade() {
+        Some(state) => {
+            let mut state = state.lock().unwrap();
+            state.entries.append(batch);
+            state.done = done;
+            true
+        },
+        None => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use super::super::list::FileType;
+
+    #[test]
+    fn streams_everything() {
+        let entries = (0..3000).map(|n| Entry {
+            path: PathBuf::from(n.to_string()),
+            file_type: FileType::File,
+            key: None,
+        });
+        let mut loader = Loader::start(entries);
+        let mut copy = loader.clone();
+
+        let mut read = vec![];
+        while !loader.done() {
+            read.extend(loader.poll());
+        }
+        assert_eq!(read.len(), 3000);
+        assert_eq!(read[2999].path, PathBuf::from("2999"));
+        assert!(loader.poll().is_empty());
+
+        // The copy still has it all to come.
+        assert_eq!(copy.poll().len(), 3000);
+    }
+}
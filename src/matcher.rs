@@ -0,0 +1,221 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use super::list::Entry;
+
+// Matches are handed over this many candidates at a time.
+const CHUNK: usize = 4096;
+
+#[derive(Debug, Default)]
+struct State {
+    base: Arc<Vec<Entry>>,
+    // The search on screen, to redo when the base grows.
+    query: Option<String>,
+    // Finished searches, each extending the one before it.
+    finished: Vec<(String, Arc<Vec<Entry>>)>,
+    matches: Vec<Entry>,
+    done: bool,
+    // Bumped by every search; a worker on an older one gives up.
+    generation: usize,
+    // Bumped whenever matches change.
+    version: usize,
+}
+
+/// Fuzzy search on a worker thread. A query that extends a finished one
+/// only looks through its matches, and backspacing to one is free.
+#[derive(Debug, Clone, Default)]
+pub struct Matcher {
+    shared: Arc<(Mutex<State>, Condvar)>,
+}
+
+impl PartialEq for Matcher {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+}
+
+impl Matcher {
+    /// Search these from now on, forgetting earlier searches.
+    pub fn start(&self, base: Vec<Entry>) {
+        let mut state = self.shared.0.lock().unwrap();
+        state.generation += 1;
+        state.base = Arc::new(base);
+        state.query = None;
+        state.finished.clear();
+        state.matches.clear();
+        // Nothing to wait for until the first search.
+        state.done = true;
+    }
+
+    pub fn search(&self, query: &str) {
+        let (lock, done) = &*self.shared;
+        let mut state = lock.lock().unwrap();
+        state.generation += 1;
+        state.version += 1;
+        state.query = Some(query.to_string());
+        state.finished.retain(|(finished, _)| query.starts_with(finished.as_str()));
+        if let Some((_, matches)) = state.finished.last().filter(|(finished, _)| finished == query) {
+            state.matches = matches.to_vec();
+            state.done = true;
+            done.notify_all();
+            return;
+        }
+        let candidates = match state.finished.last() {
+            Some((_, matches)) => matches.clone(),
+            None => state.base.clone()
+        };
+        state.matches.clear();
+        state.done = false;
+
+        let generation = state.generation;
+        let shared = self.shared.clone();
+        let query = query.to_string();
+        thread::spawn(move || {
+            let matcher = SkimMatcherV2::default();
+            let mut found = vec![];
+            for chunk in candidates.chunks(CHUNK) {
+                found.extend(chunk.iter().filter(|entry| matcher.fuzzy_match(&entry.path.to_string_lossy(), &query).is_some()).cloned());
+                let mut state = shared.0.lock().unwrap();
+                if state.generation != generation {
+                    return;
+                }
+                state.matches.append(&mut found);
+                state.version += 1;
+            }
+
+            let (lock, done) = &*shared;
+            let mut state = lock.lock().unwrap();
+            if state.generation != generation {
+                return;
+            }
+            // The order fuzzy_score::order ranks them in.
+            state.matches.sort_by(|a, b| b.path.cmp(&a.path));
+            let matches = Arc::new(state.matches.clone());
+            state.finished.push((query, matches));
+            state.done = true;
+            state.version += 1;
+            done.notify_all();
+        });
+    }
+
+    /// Entries that came in after start, e.g. the rest of a dir still being
+    /// read. Finished searches didn't see them, so the one on screen starts over.
+    pub fn extend(&self, more: Vec<Entry>) {
+        if more.is_empty() {
+            return;
+        }
+        let query = {
+            let mut state = self.shared.0.lock().unwrap();
+            Arc::make_mut(&mut state.base).extend(more);
+            state.finished.clear();
+            state.query.clone()
+        };
+        if let Some(query) = query {
+            self.search(&query);
+        }
+    }
+
+    /// Block until the search is done.
+    pub fn finish(&self) {
+        let (lock, done) = &*self.shared;
+        let mut state = lock.lock().unwrap();
+        while !state.done {
+            state = done.wait(state).unwrap();
+        }
+    }
+
+    /// Block until the search is done, or for timeout at most. True if done.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let (lock, done) = &*self.shared;
+        let until = Instant::now() + timeout;
+        let mut state = lock.lock().unwrap();
+        while !state.done {
+            let left = until.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return false;
+            }
+            state = done.wait_timeout(state, left).unwrap().0;
+        }
+
+        true
+    }
+
+    /// The matches so far, if they've changed since seen.
+    pub fn matches(&self, seen: &mut usize) -> Option<Vec<Entry>> {
+        let state = self.shared.0.lock().unwrap();
+        if state.version == *seen {
+            return None;
+        }
+        *seen = state.version;
+
+        Some(state.matches.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use super::super::list::FileType;
+
+    fn names(entries: Vec<Entry>) -> Vec<String> {
+        entries.into_iter().map(|entry| entry.path.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn narrow_and_widen() {
+        let matcher = Matcher::default();
+        let base = ["lib", "crate", "src", "credits"].iter().map(|name| Entry {
+            path: PathBuf::from(name),
+            file_type: FileType::File,
+            key: None,
+        });
+        matcher.start(base.collect());
+        let mut seen = 0;
+
+        matcher.search("cr");
+        assert!(matcher.wait(Duration::from_secs(5)));
+        assert_eq!(names(matcher.matches(&mut seen).unwrap()), vec!["credits", "crate"]);
+        assert_eq!(matcher.matches(&mut seen), None);
+
+        matcher.search("cred");
+        assert!(matcher.wait(Duration::from_secs(5)));
+        assert_eq!(names(matcher.matches(&mut seen).unwrap()), vec!["credits"]);
+
+        // Backspacing brings back the finished search.
+        matcher.search("cr");
+        assert!(matcher.wait(Duration::from_millis(0)));
+        assert_eq!(names(matcher.matches(&mut seen).unwrap()), vec!["credits", "crate"]);
+
+        matcher.search("s");
+        assert!(matcher.wait(Duration::from_secs(5)));
+        assert_eq!(names(matcher.matches(&mut seen).unwrap()), vec!["src", "credits"]);
+    }
+
+    #[test]
+    fn late_entries_join_the_search() {
+        let entry = |name: &str| Entry {
+            path: PathBuf::from(name),
+            file_type: FileType::File,
+            key: None,
+        };
+        let matcher = Matcher::default();
+        matcher.start(vec![entry("crate"), entry("lib")]);
+        let mut seen = 0;
+
+        matcher.search("cr");
+        assert!(matcher.wait(Duration::from_secs(5)));
+        assert_eq!(names(matcher.matches(&mut seen).unwrap()), vec!["crate"]);
+
+        matcher.extend(vec![entry("credits"), entry("src")]);
+        assert!(matcher.wait(Duration::from_secs(5)));
+        assert_eq!(names(matcher.matches(&mut seen).unwrap()), vec!["credits", "crate"]);
+
+        // Backspacing to a search from before sees them too.
+        matcher.search("c");
+        assert!(matcher.wait(Duration::from_secs(5)));
+        assert_eq!(names(matcher.matches(&mut seen).unwrap()), vec!["src", "credits", "crate"]);
+    }
+}
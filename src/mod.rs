@@ -1,60 +1,123 @@
 pub mod list;
+pub mod matcher;
 pub mod terminal;
 pub mod fixtures;
-
-use list::Entry;
-
-use std::env;
+pub mod config;
+pub mod opener;
+pub mod keymap;
+pub mod loader;
+pub mod pages;
+pub mod bookmarks;
+pub mod commands;
+pub mod error;
+pub mod du;
+pub mod frecency;
+pub mod history;
+pub mod tabs;
+pub mod tree;
+pub mod watch;
+pub mod status;
+
+
+use std::collections::{BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::fs::{create_dir_all, metadata, OpenOptions};
 use list::List;
+use config::Config;
+use error::Error;
 use fixtures::command_assistors;
 use termion::input::TermRead;
-use termion::event::Key;
+use termion::event::{Event, Key, MouseButton, MouseEvent};
 use termion::raw::{IntoRawMode, RawTerminal};
-use std::io::{ Write, stdout, stdin, StdoutLock};
+use std::io::{ Write, stdout, StdoutLock};
+use std::os::unix::ffi::OsStrExt;
 use termion::screen::AlternateScreen;
+use ansi_term::{Colour, Style};
 use easy_hasher::easy_hasher::*;
 
 pub mod app {
     use super::*;
 
-    pub fn run<P: AsRef<Path>>(path: P, all: bool, test: bool, fzf_hook_path: Option<PathBuf>, fzc_hook_path: Option<PathBuf>, fzd_hook_path: Option<PathBuf>) -> LsKey {
+    pub fn run<P: AsRef<Path>>(path: P, test: bool, config: Config, shell_output: ShellOutput) -> LsKey {
         if test {
             let mut path = path.as_ref().to_path_buf();
             create_dir_all(&path).expect("Failed to create directories.");
             path.push(".lsk_test_output");
             std::fs::File::create(&path).expect("failed to create lsk output file");
         }
-        let path = path.as_ref();
-        let mut ls_key = LsKey::new(path, all, test, fzf_hook_path.clone(), fzc_hook_path.clone(), fzd_hook_path.clone());
+        let mut ls_key = LsKey::with_config(path, test, &config);
+        ls_key.shell_output = shell_output;
         ls_key.update_file_display(false);
         ls_key.run_cmd();
-        let mut list = ls_key.list.clone();
 
         while ls_key.is_fuzzed {
-            ls_key.list = list;
-            let display = ls_key.display.clone();
-            if let Some(fuzzy_list) = ls_key.fuzzy_list.clone() {
-                let _list = ls_key.list;
-                ls_key = LsKey::new(path, all, test, fzf_hook_path.clone(), fzc_hook_path.clone(), fzd_hook_path.clone());
-                ls_key.list = fuzzy_list.clone();
-                ls_key.display = display;
+            if let Some(fuzzy_list) = ls_key.fuzzy_list.take() {
+                ls_key.list = fuzzy_list;
+                ls_key.reread();
             } else if !ls_key.halt {
-                let _list = ls_key.list;
-                ls_key = LsKey::new(path, all, test, fzf_hook_path.clone(), fzc_hook_path.clone(), fzd_hook_path.clone());
-                ls_key.list = _list;
-                ls_key.display = display;
+                ls_key.fuzzy_query = None;
+                ls_key.reread();
             }
             ls_key.update_file_display(false);
             ls_key.run_cmd();
-            list = ls_key.list.clone();
         }
 
         ls_key
     }
 }
 
+/// Where `w` and `r` hand their results back to the calling shell.
+/// The wrappers printed by `lsk init <shell>` read these files on exit.
+/// When unset, `w` prints the dir to stdout once lsk has left the screen,
+/// and `r` refuses, as there's nowhere to put the paths mid-session.
+///
+/// With `pick` (lsk --pick), keying a file, `r <keys>` and `w` pick the
+/// path(s) and quit, leaving them in `LsKey::picked` for the caller to print.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ShellOutput {
+    pub choosedir: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub pick: bool,
+}
+
+impl ShellOutput {
+    pub fn choose_dir<P: AsRef<Path>>(&self, dir: P) -> Result<(), std::io::Error> {
+        let dir = dir.as_ref().as_os_str().as_bytes();
+        match &self.choosedir {
+            Some(file) => std::fs::write(file, dir),
+            None => {
+                let mut stdout = stdout();
+                stdout.write_all(dir)?;
+                stdout.write_all(b"\n")
+            }
+        }
+    }
+
+    // One path per line, after any an earlier r returned.
+    pub fn return_paths(&self, paths: &[PathBuf]) -> error::Result<()> {
+        let file = self.output.as_ref()
+            .ok_or_else(|| Error::Missing("nowhere to return paths to, start lsk with --output or through lsk init".to_string()))?;
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file)
+            .and_then(|mut output| output.write_all(&separate_paths(paths, b'\n')))
+            .map_err(Error::at(file))
+    }
+}
+
+pub fn separate_paths(paths: &[PathBuf], separator: u8) -> Vec<u8> {
+    let mut bytes: Vec<u8> = vec![];
+    for path in paths {
+        bytes.extend_from_slice(path.as_os_str().as_bytes());
+        bytes.push(separator);
+    }
+
+    bytes
+}
+
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct LsKey {
     pub list: List,
@@ -73,283 +136,716 @@ pub struct LsKey {
     pub fzd_hook_path: Option<PathBuf>,
     pub default_editor: String,
     pub default_opener: String,
+    pub colors: list::Colors,
+    pub open_rules: Vec<opener::Rule>,
+    pub keymap: keymap::Keymap,
+    pub history: history::History,
+    // The fuzzy query typed in this dir, so going back can retype it.
+    pub fuzzy_query: Option<String>,
+    pub pending_prompt: Option<String>,
+    pub tabs: tabs::Tabs,
+    // The right pane in dual-pane mode, keyed b1, b2, ...
+    pub pane: Option<List>,
+    pub tree: Option<tree::Tree>,
+    pub matcher: matcher::Matcher,
+    pub watcher: Option<watch::Watcher>,
+    // Shown above the prompt until the next key.
+    pub notice: Option<String>,
+    // What last went wrong, shown in the notice's place until the next key.
+    pub error: Option<String>,
+    // The page of the ? overlay on screen, while it's up.
+    pub help: Option<usize>,
+    // What the last c <cmd> printed, shown in the overlay instead of the help.
+    pub output: Option<Vec<String>>,
+    // How the list last split into pages; shared, as clones are common.
+    pub pages: Option<Arc<pages::Pages>>,
+    // Where the keys on screen are, for mouse clicks.
+    pub spots: Vec<pages::Spot>,
+    // And the right pane's, counted from the screen's left edge.
+    pub pane_spots: Vec<pages::Spot>,
+    pub mouse: bool,
+    // Right-clicked entries, by full path.
+    pub marks: BTreeSet<PathBuf>,
+    // The matcher results last shown.
+    pub matched: usize,
+    pub shell_output: ShellOutput,
+    pub picked: Vec<PathBuf>,
+    pub status: status::Template,
+    // The status bar as of the last update_file_display, None when it's off.
+    pub status_bar: Option<String>,
+    // The status bar's fields that cost a stat per entry or a read of the
+    // dir, as worked out for summary_for: the dir, how many entries it had
+    // once read in full (None while it streams in) and how many dirs du had
+    // sized. Redone when any of those change.
+    pub summary: status::Summary,
+    pub summary_for: Option<(PathBuf, Option<usize>, usize)>,
+    // Shown as ~ in the status bar.
+    pub home: Option<PathBuf>,
 }
 
 impl LsKey {
+    // Default settings plus the env vars; see with_config for the config file.
     pub fn new<P: AsRef<Path>>(path: P, all: bool, test: bool, fzf_hook_path: Option<PathBuf>, fzc_hook_path: Option<PathBuf>, fzd_hook_path: Option<PathBuf>) -> Self {
-        let mut ls_key: LsKey = Default::default();
-        let list = if all {
-           list::List::new(path)
-               .list_include_hidden()
-               .unwrap()
-        } else {
-           list::List::new(path)
-               .list_skip_hidden()
-               .unwrap()
-        };
-
-        match env::var("EDITOR") {
-            Ok(editor) => {
-                 ls_key.default_editor = editor
-            },
-            Err(_) => {
-                ls_key.default_editor = "nano".to_string()
-            }
-        };
+        let mut config = Config::default();
+        config.apply_env();
+        config.all = all;
+        config.hooks.fuzzy_find = fzf_hook_path;
+        config.hooks.fuzzy_cmd = fzc_hook_path;
+        config.hooks.fuzzy_dir = fzd_hook_path;
+
+        LsKey::with_config(path, test, &config)
+    }
 
-        match env::var("LSK_FILE_OPENER") {
-            Ok(editor) => {
-                 ls_key.default_opener = editor
-            },
-            Err(_) => {
-                ls_key.default_opener = "xdg-open".to_string()
+    pub fn with_config<P: AsRef<Path>>(path: P, test: bool, config: &Config) -> Self {
+        let mut ls_key: LsKey = Default::default();
+        let mut list = list::List::new(path);
+        list.sort = config.sort;
+        list.du = if config.du { Some(du::Du::default()) } else { None };
+        // Somewhere unreadable still opens, empty, saying why.
+        match list.clone().stream(config.all) {
+            Ok(list) => ls_key.list = list,
+            Err(e) => {
+                ls_key.list = list;
+                ls_key.error = Some(e.to_string());
             }
-        };
-
-        ls_key.list = list;
-        ls_key.all = all;
+        }
+        ls_key.all = config.all;
         ls_key.halt = true;
         ls_key.is_fuzzed = false;
         ls_key.test = test;
-        ls_key.fzf_hook_path = fzf_hook_path;
-        ls_key.fzc_hook_path = fzc_hook_path;
-        ls_key.fzd_hook_path = fzd_hook_path;
+        ls_key.fzf_hook_path = config.hooks.fuzzy_find.clone();
+        ls_key.fzc_hook_path = config.hooks.fuzzy_cmd.clone();
+        ls_key.fzd_hook_path = config.hooks.fuzzy_dir.clone();
+        ls_key.default_editor = config.editor.clone();
+        ls_key.default_opener = config.opener.clone();
+        ls_key.colors = config.colors.clone();
+        ls_key.open_rules = config.open.clone();
+        // Config::parse already checked it.
+        ls_key.keymap = config.keymap().unwrap_or_default();
+        ls_key.tree = config.tree.map(tree::Tree::new);
+        ls_key.mouse = config.mouse;
+        // Config::parse checked this too.
+        ls_key.status = status::Template::parse(&config.status).unwrap_or_default();
+        ls_key.home = std::env::var_os("HOME").map(PathBuf::from);
 
         ls_key
     }
 
-    fn fuzzy_score(&mut self, mut input: String) -> list::fuzzy_score::Scores {
-        let files = &self.list.files;
-
-        let mut input_vec_str: Vec<&str> = input.split(" ").collect();
-
-        if input_vec_str.iter().count() > 1{
-            input_vec_str.pop();
-            input = input_vec_str.into_iter().collect();
+    /// Fuzzy search the list self.matcher was started on, on its worker
+    /// thread. Long searches show partial results.
+    pub fn fuzzy_search(&mut self, input: &str) {
+        let mut words: Vec<&str> = input.split(' ').collect();
+        if words.len() > 1 {
+            words.pop();
         }
-        let score_list = |entry: Entry| {
-            (
-             entry.clone(),
-             list::fuzzy_score::score(entry.path.to_str().unwrap(), &input)
-            )
+        self.matcher.search(&words.concat());
+        // A new query's matches start on their first page.
+        self.list.page = 0;
+        // Most are done in no time, so there's no flicker of partial results.
+        self.matcher.wait(FUZZY_WAIT);
+        self.fuzzy_matched();
+    }
+
+    /// Show the matcher's latest results. True if there were new ones.
+    pub fn fuzzy_matched(&mut self) -> bool {
+        let files = match self.matcher.matches(&mut self.matched) {
+            Some(files) => files,
+            None => return false
         };
+        self.list.files = files;
+        // The rest of the dir reaches the matcher from pre_fuzz_list instead,
+        // as it's read; see the idle tick in read_process_chars.
+        self.list.loader = None;
+        let list = self.list.clone();
+        self.update_file_display(false);
+        self.fuzzy_list = Some(list);
 
-        let files_score: Vec<list::fuzzy_score::Score> =
-           files.iter()
-               .map(|file| list::fuzzy_score::Score::Files(score_list(
-                                Entry {
-                                    path: file.path.to_path_buf(),
-                                    file_type: file.file_type.clone(),
-                                    key: file.key
-                                }
-                           )
-                       )
-                )
-               .collect();
+        true
+    }
 
-        let files = files_score;
+    pub fn update(&mut self, list: List) -> error::Result<()> {
+            self.list = self.listed(list)?;
 
-        list::fuzzy_score::Scores {
-            files,
-        }
+            Ok(())
+    }
+
+    fn listed(&self, list: List) -> error::Result<List> {
+            list.stream(self.all)
     }
 
-    fn fuzzy_rank(&mut self, mut scores: list::fuzzy_score::Scores) -> list::fuzzy_score::Scores {
-        scores.files.sort_by(|a, b| list::fuzzy_score::order(a, b));
+    // Go straight to dir, wherever it is, keeping the history and sort.
+    // Staying put if it can't be read.
+    pub fn jump_to<P: AsRef<Path>>(&mut self, dir: P) -> error::Result<()> {
+        let here = self.location();
+        self.enter(dir)?;
+        self.history.push(here);
+        self.fuzzy_query = None;
+        self.record_visit();
 
-        scores
+        Ok(())
     }
 
-    // Filter out no-scores.
-    fn fuzzy_filter(&mut self, scores: list::fuzzy_score::Scores) -> list::fuzzy_score::Scores {
-         let mut files_vec: Vec<list::fuzzy_score::Score> = vec![];
-         for score in scores.files.iter() {
-             let path = score.score().0;
-             let score = score.score().1;
+    fn enter<P: AsRef<Path>>(&mut self, dir: P) -> error::Result<()> {
+        let mut list = List::new(dir.as_ref());
+        list.path_history = self.list.path_history.clone();
+        list.path_history.push(list.parent_path.clone());
+        list.sort = self.list.sort;
+        list.du = self.list.du.clone();
+        self.update(list)
+    }
 
-             let thing = (path, score.clone());
+    pub fn location(&self) -> history::Location {
+        history::Location {
+            dir: self.list.parent_path.clone(),
+            filter: self.list.filter.clone(),
+            page: self.list.page,
+            query: self.fuzzy_query.clone(),
+        }
+    }
 
-             if score.is_some() {
-                  files_vec.push(list::fuzzy_score::Score::Files(thing));
-             }
-         }
+    // Call before going somewhere new, so `-` can come back.
+    fn leave(&mut self) {
+        self.history.push(self.location());
+        self.fuzzy_query = None;
+    }
 
-         list::fuzzy_score::Scores {
-             files: files_vec,
-         }
+    // Back to reading the prompt after a search, on the list as it stands.
+    // The fuzzy query is kept, as it's still narrowing the list.
+    fn reread(&mut self) {
+        self.input = Input::new();
+        self.fuzzy_list = None;
+        self.pre_fuzz_list = None;
+        self.halt = true;
+        self.is_fuzzed = false;
     }
 
-    pub fn fuzzy_update(&mut self, input: String) -> Self {
-        let scores = self.fuzzy_score(input);
-        let scores = self.fuzzy_rank(scores);
-        let scores = self.fuzzy_filter(scores);
-        let list = self.scores_to_list(scores);
-        self.update_file_display(false);
-        self.fuzzy_list = Some(list);
+    fn session(&self) -> tabs::Session {
+        tabs::Session {
+            list: self.list.clone(),
+            history: self.history.clone(),
+            fuzzy_query: self.fuzzy_query.clone(),
+        }
+    }
 
-        self.clone()
+    // The dir is re-read, since another tab may have changed it. If it's
+    // gone unreadable, the tab comes back as it was left, saying so.
+    fn load_session(&mut self, session: tabs::Session) -> error::Result<()> {
+        let res = self.enter(&session.list.parent_path);
+        if res.is_err() {
+            self.list = session.list.clone();
+        }
+        self.list.path_history = session.list.path_history;
+        self.list.filter = session.list.filter;
+        self.list.page = session.list.page;
+        self.history = session.history;
+        self.fuzzy_query = None;
+        self.pending_prompt = session.fuzzy_query.map(|query| format!("s {}", query));
+
+        res
     }
 
+    // The page is put back as is; the query is retyped at the next prompt.
+    fn restore(&mut self, location: history::Location) -> error::Result<()> {
+        self.enter(&location.dir)?;
+        self.list.filter = location.filter;
+        self.list.page = location.page;
+        self.fuzzy_query = None;
+        self.pending_prompt = location.query.map(|query| format!("s {}", query));
+
+        Ok(())
+    }
 
-    pub fn scores_to_list(&mut self, scores: list::fuzzy_score::Scores) -> list::List {
-        let files_list: Vec<Entry> = scores.files.iter().map(|score|
-            Entry {
-                path: score.score().0.path,
-                file_type: score.score().0.file_type,
-                key: score.score().0.key
+    // Feeds `z`. The history is a nicety, so failing to write it is ignored.
+    fn record_visit(&self) {
+        if self.test {
+            return;
+        }
+        if let Ok(mut frecency) = frecency::Frecency::load() {
+            frecency.visit(&self.list.parent_path, frecency::now());
+            let _ = frecency.save();
+        }
+    }
+
+    // Re-read the current dir, e.g. after a subshell may have changed it.
+    pub fn refresh(&mut self) -> error::Result<()> {
+        let mut list = List::new(&self.list.parent_path);
+        list.path_history = self.list.path_history.clone();
+        list.sort = self.list.sort;
+        list.du = self.list.du.clone();
+        let res = self.update(list);
+        self.halt = false;
+        let halt = self.list.filter.is_some();
+        self.update_file_display(halt);
+
+        res
+    }
+
+    // Re-read the dir in place, keeping the page. If an entry that's still
+    // there ended up with a different key, say so, and carry the filter over
+    // by path so it still shows the same entries. A dir that's gone
+    // unreadable stays as it was, with the error up.
+    fn relist(&mut self) {
+        let keys = |list: &mut List| -> HashMap<PathBuf, usize> {
+            list.order_and_sort_list(true, false)
+                .into_iter()
+                .filter_map(|entry| Some((entry.path, entry.key?)))
+                .collect()
+        };
+        let before = keys(&mut self.list);
+        let mut list = List::new(&self.list.parent_path);
+        list.path_history = self.list.path_history.clone();
+        list.sort = self.list.sort;
+        list.du = self.list.du.clone();
+        list.filter = self.list.filter.clone();
+        list.page = self.list.page;
+        self.list = match self.listed(list) {
+            Ok(list) => list,
+            Err(e) => {
+                self.error = Some(e.to_string());
+                return;
             }
-        ).collect();
+        };
+        let after = keys(&mut self.list);
+        // A file renamed or rewritten in place leaves the count as it was.
+        self.summary_for = None;
+        if after.iter().any(|(path, key)| before.get(path).is_some_and(|old| old != key)) {
+            let paths: HashMap<usize, &PathBuf> = before.iter().map(|(path, key)| (*key, path)).collect();
+            // Nothing left to show is no filter at all.
+            self.list.filter = self.list.filter.take()
+                .map(|filter| filter.iter().filter_map(|key| after.get(*paths.get(key)?)).copied().collect::<Vec<usize>>())
+                .filter(|filter| !filter.is_empty());
+            self.notice = Some("listing changed".to_string());
+        }
+        self.update_file_display(self.list.filter.is_some());
+    }
 
-        self.list.files = files_list;
+    // True if files came or went here since last asked.
+    fn dir_changed(&mut self) -> bool {
+        if self.watcher.as_ref().map(|watcher| &watcher.dir) != Some(&self.list.parent_path) {
+            self.watcher = Some(watch::Watcher::new(&self.list.parent_path));
+        }
 
-        self.list.clone()
+        self.watcher.as_ref().is_some_and(|watcher| watcher.changed())
     }
 
-    pub fn update(&mut self, list: List) {
-            let list = if self.all {
-                   list
-                   .list_include_hidden()
-                   .unwrap()
+   pub fn update_file_display(&mut self, filter: bool) {
+            let (header, labels, keys) = match self.tree {
+                Some(_) => self.tree_labels(),
+                None => self.grid_labels(filter)
+            };
+            let (columns, height) = termion::terminal_size().unwrap_or((80, 24));
+            let width = match self.pane {
+                Some(_) => usize::from(columns).saturating_sub(PANE_GAP) / 2,
+                None => usize::from(columns)
+            };
+            // The test harness hashes the grid, so it's left the whole screen.
+            self.status_bar = if self.test || self.status.is_empty() {
+                None
             } else {
-                   list
-                   .list_skip_hidden()
-                   .unwrap()
+                let summary = self.summary();
+                Some(self.status.render(&summary))
             };
-
-            self.list = list;
+            let rows = self.grid_rows(height);
+            let shape = pages::Shape { width, rows, one_column: self.test || self.tree.is_some() };
+
+            let pages = match self.pages.take() {
+                Some(old) if old.shows(&header, &labels) && old.shape() == shape => old,
+                Some(old) if old.shows(&header, &labels) => {
+                    // Resized: stay on whichever page now has the old page's first entry.
+                    let first = old.range(self.list.page).start;
+                    let pages = pages::Pages::new(header, labels, keys, shape);
+                    self.list.page = pages.page_of(first);
+                    Arc::new(pages)
+                },
+                _ => Arc::new(pages::Pages::new(header, labels, keys, shape))
+            };
+            self.list.page = self.list.page.min(pages.count() - 1);
+
+            let mut display = pages.render(self.list.page);
+            self.spots = pages.spots(self.list.page, &display);
+            self.pane_spots = vec![];
+            if self.pane.is_some() {
+                let column = width + PANE_GAP;
+                let (pane, spots) = self.pane_display(width, rows);
+                let left: Vec<&str> = display.lines().collect();
+                // side_by_side starts the right side at column, or just past a left line wider than that.
+                self.pane_spots = spots.into_iter().map(|spot| {
+                    let at = left.get(spot.line).map_or(0, |line| terminal::input_n_display::visible_width(line) + 1).max(column);
+                    pages::Spot { columns: spot.columns.start + at..spot.columns.end + at, ..spot }
+                }).collect();
+                display = terminal::input_n_display::side_by_side(&display, &pane, column);
+            }
+            // The keys stay as they are until the rest of a big dir is in; see List::catch_up.
+            let loading = if self.list.loader.is_some() { ", loading…" } else { "" };
+            if pages.count() > 1 {
+                display.push_str(&format!("page {}/{}{}\n", self.list.page + 1, pages.count(), loading));
+            } else if self.list.loader.is_some() {
+                display.push_str("loading…\n");
+            }
+            self.display = Some((self.list.parent_path.clone(), display));
+            self.pages = Some(pages);
     }
 
-   pub fn update_file_display(&mut self, mut filter: bool) {
-            let mut go = true;
-            let entries_count = self.list.files.iter().count();
-            let mut start = 0;
-            let mut end = entries_count;
-            if let Some(ls) = &self.list.filter {
-                start = ls.clone().into_iter().nth(0).unwrap();
-                end = *ls.into_iter().last().unwrap();
+    // The ../dir header, then each entry with its key.
+    fn grid_labels(&mut self, filter: bool) -> (Option<String>, Vec<String>, Vec<usize>) {
+            self.measure(self.list.subdirs());
+            let mut entries = self.list.order_and_sort_list(true, false);
+            if filter {
+                entries = list::filter_entries(entries, &self.list.filter);
+            }
+            let header = match entries.first() {
+                Some(entry) if entry.key == Some(0) => list::key_entries(&self.list, vec![entries.remove(0)], &self.colors).pop(),
+                _ => None
+            };
+            let keys = entries.iter().filter_map(|entry| entry.key).collect();
+            let paths: Vec<PathBuf> = if self.marks.is_empty() {
+                vec![]
             } else {
+                entries.iter().map(|entry| self.list.parent_path.join(&entry.path)).collect()
+            };
+            let mut labels = list::key_entries(&self.list, entries, &self.colors);
+            for (label, path) in labels.iter_mut().zip(&paths) {
+                *label = self.marked(path, label.clone());
             }
-            while go {
-                let entries = self.list.order_and_sort_list(true, filter);
-                let mut entries_keyed: Vec<String> = list::key_entries(entries.clone());
-                if end  < entries_count {
-                    let last = format!("[{}...{}]", end, entries_count);
-                    entries_keyed.push(last);
-                }
-                let res = terminal::input_n_display::grid(entries_keyed.clone());
-                if let Some(r) = res {
-                    let grid = r.0;
-                    let width = r.1;
-                    let height = r.2;
-                    let display: terminal::input_n_display::Display;
-                    let _display = grid.fit_into_width(width);
-                    let pad: usize;
-                    if _display.is_some() && !self.test {
-                         display = _display.unwrap(); // Safe to unwrap.
-                         pad = 4;
-                    } else {
-                         display = grid.fit_into_columns(1);
-                         pad = 5;
-                    }
-                    let grid_row_count = display.row_count();
-                    if (grid_row_count + pad) > height {
-                        //panic!("Can't fit list into screen.");
-                        //
 
-                        let mut filter_vec: Vec<usize> = vec![];
+            (header, labels, keys)
+    }
+
+    // Below the notice, tab bar and prompt, with a line for the page count
+    // and one for the status bar if it's on.
+    fn grid_rows(&self, height: u16) -> usize {
+        let status_bar = if self.status_bar.is_some() { 1 } else { 0 };
 
-                        let diff = (grid_row_count + pad).checked_sub(height).unwrap_or(1);
+        usize::from(height).saturating_sub(4 + status_bar).max(1)
+    }
 
-                        end = end.checked_sub(diff).unwrap_or(end);
-                        let range = start..end;
+    // What the status bar's fields are filled in with. The counts, sort,
+    // filter and marks are worked out every time, the rest only once the
+    // listing has changed; see summary_for.
+    fn summary(&mut self) -> status::Summary {
+        let read = if self.list.loader.is_some() { None } else { Some(self.list.files.len()) };
+        let summary_for = (self.list.parent_path.clone(), read, self.measured());
+        if self.summary_for.as_ref() != Some(&summary_for) {
+            self.summary = self.dir_summary();
+            self.summary_for = Some(summary_for);
+        }
+        let dirs = self.list.files.iter().filter(|entry| entry.file_type == list::FileType::Dir).count();
+        let sort = if self.list.sort.reverse {
+            format!("by {}, reversed", self.list.sort.by)
+        } else {
+            format!("by {}", self.list.sort.by)
+        };
+        // A fuzzy search narrows the list itself, so it's named rather than its keys.
+        let filter = match (&self.fuzzy_query, &self.list.filter) {
+            (Some(query), _) => Some(format!("s {}", query)),
+            (None, Some(keys)) => match (keys.first(), keys.last()) {
+                (Some(first), Some(last)) if keys.windows(2).all(|pair| pair[1] == pair[0] + 1) => Some(format!("{}-{}", first, last)),
+                _ => Some(format!("{} keys", keys.len()))
+            },
+            (None, None) => None
+        };
 
-                        range.into_iter().for_each(|i|
-                            filter_vec.push(i)
-                        );
+        status::Summary {
+            files: self.list.files.len() - dirs,
+            dirs,
+            sort,
+            filter,
+            marks: self.marks.len(),
+            ..self.summary.clone()
+        }
+    }
 
-                        self.list.filter = Some(filter_vec);
-                        filter = true;
+    fn dir_summary(&self) -> status::Summary {
+        status::Summary {
+            path: status::tilde(&self.list.parent_path, self.home.as_deref()),
+            hidden: self.list.hidden_count(),
+            size: match self.list.total_size() {
+                bytes if bytes < 1024 => format!("{}B", bytes),
+                bytes => du::human(bytes)
+            },
+            branch: status::git_branch(&self.list.parent_path),
+            ..Default::default()
+        }
+    }
 
+    // One node per line, indented under its dir.
+    fn tree_labels(&self) -> (Option<String>, Vec<String>, Vec<usize>) {
+        let nodes = self.tree_nodes();
+        self.measure(nodes.iter().filter(|node| node.is_dir).map(|node| node.path.clone()).collect());
+        let shown: Vec<&tree::Node> = nodes
+            .iter()
+            .filter(|node| self.list.filter.as_ref().is_none_or(|filter| filter.contains(&node.key)))
+            .collect();
+
+        let name = self.list.parent_path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let header = self.colors.dir().bold().paint(format!("../{}", name)).to_string();
+        let mut lines = vec![];
+        for node in shown.iter() {
+            let name = node.path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            let colour = if node.is_dir { self.colors.dir() } else { self.colors.file() };
+            let label = colour.bold().paint(format!("{} [{}]", name, node.key)).to_string();
+            let mut line = format!("{}{}", node.prefix, self.marked(&node.path, label));
+            if let Some(size) = self.list.du.as_ref().filter(|_| node.is_dir).and_then(|du| du.size(&node.path)) {
+                line = format!("{} {}", line, du::human(size));
+            }
+            lines.push(line);
+        }
 
-                    } else {
-                       go = false;
-                    }
+        (Some(header), lines, shown.iter().map(|node| node.key).collect())
+    }
 
-                    self.display = Some((self.list.parent_path.clone(), display.to_string()));
-                } else {
-                    go = false;
-                }
+    // Marked entries get a * in front.
+    fn marked(&self, path: &Path, label: String) -> String {
+        if self.marks.contains(path) {
+            format!("{}{}", Style::new().bold().paint("*"), label)
+        } else {
+            label
+        }
+    }
+
+    // Mark the keyed entry, or unmark it if it's marked.
+    fn toggle_mark(&mut self, key: usize) {
+        if let Some(path) = self.file_by_key(key, false).ok().filter(|_| key != 0) {
+            if !self.marks.remove(&path) {
+                self.marks.insert(path);
             }
+        }
+        self.update_file_display(self.list.filter.is_some());
     }
 
-    fn return_file_by_key_mode(&mut self, input: Input, is_fuzzed: bool) {
-        let get_file = |key_string: String| {
-             let key: usize = key_string.parse().unwrap();
-             self.list.get_file_by_key(key, !is_fuzzed).unwrap()
+    // The key drawn at column x, row y of the screen, both from 1, and
+    // which pane it's in: 'a' for the left, 'b' for the right.
+    fn key_at(&self, x: u16, y: u16) -> Option<(char, usize)> {
+        let display = &self.display.as_ref()?.1;
+        let (columns, _) = termion::terminal_size().unwrap_or((80, 24));
+        let columns = usize::from(columns).max(1);
+        let (x, y) = (usize::from(x).checked_sub(1)?, usize::from(y));
+        let mut row = GRID_ROW;
+        for (line, text) in display.lines().enumerate() {
+            let rows = terminal::input_n_display::visible_width(text).max(1).div_ceil(columns);
+            if (row..row + rows).contains(&y) {
+                let column = (y - row) * columns + x;
+                let at = |spots: &[pages::Spot]| spots.iter().find(|spot| spot.line == line && spot.columns.contains(&column)).map(|spot| spot.key);
+                return at(&self.spots).map(|key| ('a', key)).or_else(|| at(&self.pane_spots).map(|key| ('b', key)));
+            }
+            row += rows;
+        }
+
+        None
+    }
+
+    // Show the page key is on.
+    fn show_key(&mut self, key: usize) {
+        self.list.filter = None;
+        self.update_file_display(false);
+        self.list.page = self.pages.as_ref().map_or(0, |pages| pages.page_of_key(key));
+        self.update_file_display(false);
+    }
+
+    // Forward or back by pages, stopping at either end.
+    fn turn_page(&mut self, by: isize) {
+        let last = self.pages.as_ref().map_or(0, |pages| pages.count() - 1);
+        self.list.page = self.list.page.saturating_add_signed(by).min(last);
+        self.update_file_display(self.list.filter.is_some());
+    }
+
+    // Have du size these dirs, and the right pane's, in the background.
+    fn measure(&self, mut dirs: Vec<PathBuf>) {
+        if let Some(du) = &self.list.du {
+            if let Some(pane) = &self.pane {
+                dirs.extend(pane.subdirs());
+            }
+            du.request(dirs);
+        }
+    }
+
+    // How many du sizes are in; a change means there's something to redraw.
+    fn measured(&self) -> usize {
+        self.list.du.as_ref().map_or(0, |du| du.done())
+    }
+
+    // ? puts the help over the grid until the next key.
+    fn help_mode(&mut self) {
+        self.help = Some(0);
+        self.output = None;
+
+        self.halt = false;
+        let halt = self.list.filter.is_some();
+        self.update_file_display(halt);
+        if !halt {
+            self.run_cmd();
+        }
+    }
+
+    // The overlay, paged to fit the grid's space.
+    fn help_pages(&self) -> pages::Pages {
+        let hooks = [
+            ("fuzzy_find", self.fzf_hook_path.as_deref()),
+            ("fuzzy_cmd", self.fzc_hook_path.as_deref()),
+            ("fuzzy_dir", self.fzd_hook_path.as_deref()),
+        ];
+        let lines = match &self.output {
+            Some(output) => output.clone(),
+            None => commands::help(&self.keymap, &hooks)
         };
+        let (columns, height) = termion::terminal_size().unwrap_or((80, 24));
+        let shape = pages::Shape {
+            width: usize::from(columns),
+            rows: self.grid_rows(height),
+            one_column: true,
+        };
+        let keys = (0..lines.len()).collect();
 
-        let mut n = 0;
-        let mut format_cmd = |key: PathBuf| {
-                    n +=1;
-                    let file_string = get_file(key.to_str().unwrap().to_string()).to_str().unwrap().to_string();
-                    let file_string = file_string.replace(" ", r"\ ");
-                   format!(r#"{}={}"#, n, file_string)
+        pages::Pages::new(None, lines, keys, shape)
+    }
+
+    fn turn_help(&mut self, by: isize) {
+        let last = self.help_pages().count() - 1;
+        self.help = self.help.map(|page| page.saturating_add_signed(by).min(last));
+    }
+
+    // du turns dir sizes on or off.
+    fn du_mode(&mut self) {
+        let du = match self.list.du {
+            Some(_) => None,
+            None => Some(du::Du::default())
         };
+        self.list.du = du.clone();
+        if let Some(pane) = self.pane.as_mut() {
+            pane.du = du;
+        }
 
-        if let Some (r) = input.args {
-            let _output_vec: Vec<std::process::Output> =
-                r.iter()
-                    .map(|key|
-                         format_cmd(PathBuf::from(key))
-                    ).map(|statement|
-                        format!(r#""$(printf '{} \n ')""#, statement)
-                    ).map(|cmd|
-                        terminal::parent_shell::type_text(
-                            cmd,
-                            0
-                        )
-                    ).collect();
+        self.halt = false;
+        let halt = self.list.filter.is_some();
+        self.update_file_display(halt);
+        if !halt {
+            self.run_cmd();
+        }
+    }
+
+    pub fn tree_nodes(&self) -> Vec<tree::Node> {
+        let tree = match &self.tree {
+            Some(tree) => tree,
+            None => return vec![]
+        };
+        let entries = |mut list: List| -> Vec<(PathBuf, bool)> {
+            let parent = list.parent_path.clone();
+            list.order_and_sort_list(true, false)
+                .into_iter()
+                .skip(1)
+                .map(|entry| (parent.join(&entry.path), entry.file_type == list::FileType::Dir))
+                .collect()
+        };
+        let children = |dir: &Path| {
+            let mut list = List::new(dir);
+            list.sort = self.list.sort;
+            let list = if self.all {
+                list.list_include_hidden()
+            } else {
+                list.list_skip_hidden()
+            };
+            list.map(entries).unwrap_or_default()
+        };
+
+        tree.nodes(entries(self.list.clone()), children)
+    }
+
+    // Tree-aware, so r, o and friends use the keys on screen.
+    pub fn file_by_key(&self, key: usize, is_fuzzed: bool) -> error::Result<PathBuf> {
+        if self.tree.is_some() && key != 0 {
+            self.tree_nodes().into_iter().find(|node| node.key == key).map(|node| node.path).ok_or(Error::NoKey(key))
         } else {
-            ()
+            self.list.get_file_by_key(key, !is_fuzzed)
         }
     }
 
-    pub fn filter_mode(&mut self, list: List) {
-        let input_string: String = self.input.display.iter().collect();
-        let mut input_vec_str: Vec<&str> = input_string.split("-").collect();
-        let mut key_vec: Vec<usize> = vec![];
-
-        // Does it end in "-"?
-        let last = input_vec_str.iter().last();
-        let mut open_range = false;
-        if let Some(l) = last {
-            if l == &"" {
-                open_range = true;
-           }
+    // Keying a dir flips it open or shut, 0 still goes up.
+    fn tree_key_mode(&mut self, key: usize, is_fuzzed: bool) {
+        if key == 0 {
+            return self.key_mode(self.list.clone(), key, is_fuzzed);
         }
+        let res = match self.tree_nodes().into_iter().find(|node| node.key == key) {
+            Some(node) if node.is_dir => {
+                if let Some(tree) = self.tree.as_mut() {
+                    tree.toggle(&node.path);
+                }
+                Ok(())
+            },
+            Some(node) if self.shell_output.pick => {
+                return self.pick(vec![node.path]);
+            },
+            Some(node) => self.open_file(node.path),
+            None => Err(Error::NoKey(key))
+        };
+
+        self.carry_on(res);
+    }
 
-        // Only want to deal with integers if it's an open range.
-        if open_range {
-           input_vec_str.pop();
+    // tree turns the tree view on or off, tree <depth> turns it on that deep.
+    fn tree_mode(&mut self, input: Input) {
+        match input.args.as_deref() {
+            Some([depth]) => match depth.parse() {
+                Ok(depth) => self.tree = Some(tree::Tree::new(depth)),
+                Err(_) => self.error = Some(format!("tree: '{}' isn't a depth", depth))
+            },
+            _ if self.tree.is_some() => self.tree = None,
+            _ => self.tree = Some(tree::Tree::default())
         }
+        self.list.filter = None;
+        self.list.page = 0;
 
-        // Make sure it'sall integers.
-        for i in input_vec_str.into_iter() {
-            let key: Result<usize, std::num::ParseIntError> = i.parse();
-            if key.is_ok() {
-                key_vec.push(key.unwrap());
-            }
+        self.halt = false;
+        self.update_file_display(false);
+        self.run_cmd();
+    }
 
+    fn return_file_by_key_mode(&mut self, input: Input, is_fuzzed: bool) {
+        let paths = self.files_by_keys("r", &input.args.unwrap_or_default(), is_fuzzed);
+        match paths {
+            Ok(paths) => self.return_paths(paths),
+            Err(e) => self.carry_on(Err(e))
         }
+    }
 
-        let end: usize;
-        let start = key_vec.clone().into_iter().nth(0).unwrap();
+    // Every key's path, or what's wrong with the first bad one.
+    fn files_by_keys(&self, cmd: &str, keys: &[String], is_fuzzed: bool) -> error::Result<Vec<PathBuf>> {
+        keys.iter()
+            .map(|key| {
+                let key = key.parse().map_err(|_| commands::ParseError::NotAKey(cmd.to_string(), key.clone()))?;
+                self.file_by_key(key, is_fuzzed)
+            })
+            .collect()
+    }
 
-        if open_range {
-            end =  list.files.iter().count() + 1;
-        } else {
-            end = key_vec.clone().into_iter().nth(1).unwrap() + 1;
+    // A bare r hands back the marked entries.
+    fn return_marked_mode(&mut self) {
+        let paths = self.marks.iter().cloned().collect();
+        self.return_paths(paths);
+    }
+
+    fn return_paths(&mut self, paths: Vec<PathBuf>) {
+        if self.shell_output.pick {
+            self.pick(paths);
+        } else if let Err(e) = self.shell_output.return_paths(&paths) {
+            self.carry_on(Err(Error::cmd("r")(e)));
         }
+    }
+
+    // Picking ends the session, so don't re-enter the fuzzy loop in app::run.
+    fn pick(&mut self, paths: Vec<PathBuf>) {
+        self.picked = paths;
+        self.is_fuzzed = false;
+    }
+
+    pub fn filter_mode(&mut self, start: usize, end: Option<usize>) {
+        // <key>- scrolls to the page with key on it.
+        let end = match end {
+            Some(end) => end + 1,
+            None => {
+                self.show_key(start);
+                return self.run_cmd();
+            }
+        };
 
         let range = start..end;
 
@@ -360,245 +856,508 @@ impl LsKey {
         );
 
         self.list.filter = Some(filter_vec);
+        self.list.page = 0;
         self.update_file_display(true);
         self.run_cmd()
     }
 
-    pub fn key_mode(&mut self, list: List, input: Input, is_fuzzed: bool) {
-        let key: usize = input.cmd.unwrap().parse().unwrap();
+    pub fn key_mode(&mut self, list: List, key: usize, is_fuzzed: bool) {
         match key {
             0 => {
-                 self.list.parent_path.pop();
-                 let file_pathbuf = self.list.parent_path.clone();
-                 self.list.parent_path.pop();
-                 let list = self.list.clone().update(file_pathbuf);
-                 self.update(list);
-                 self.halt = false;
-                 let halt = self.list.filter.is_some();
-                 self.update_file_display(halt);
-                 if !halt {
-                     self.run_cmd();
-                 }
+                 let mut up = self.list.clone();
+                 up.parent_path.pop();
+                 let file_pathbuf = up.parent_path.clone();
+                 up.parent_path.pop();
+                 let res = self.go(up.update(file_pathbuf));
+                 self.carry_on(res);
             },
             _ => {
-                  let file_pathbuf = list.get_file_by_key(key, !is_fuzzed).unwrap();
-                  if metadata(file_pathbuf.clone()).unwrap().is_dir() {
-                      let list = self.list.clone().update(file_pathbuf);
-                      self.update(list);
-                      self.halt = false;
-                      let halt = self.list.filter.is_some();
-                      self.update_file_display(halt);
-                      if !halt {
+                  let file = list.get_file_by_key(key, !is_fuzzed).and_then(|file_pathbuf| {
+                      let is_dir = metadata(&file_pathbuf).map_err(Error::at(&file_pathbuf))?.is_dir();
+                      Ok((file_pathbuf, is_dir))
+                  });
+                  match file {
+                      Ok((file_pathbuf, true)) => {
+                          let res = self.go(self.list.clone().update(file_pathbuf));
+                          self.carry_on(res);
+                      },
+                      Ok((file_pathbuf, false)) if self.shell_output.pick => {
+                          self.pick(vec![file_pathbuf]);
+                      },
+                      Ok((file_pathbuf, false)) => {
+                          if let Err(e) = self.open_file(file_pathbuf) {
+                              self.error = Some(e.to_string());
+                          }
+                          self.halt = true;
+                          self.update_file_display(self.halt);
                           self.run_cmd();
-                      }
-                  } else {
-                      let file_path =
-                          file_pathbuf
-                          .to_str().unwrap()
-                          .to_string();
-                      terminal::shell::spawn(self.default_editor.clone(), vec![file_path]);
-                      self.halt = true;
-                      self.update_file_display(self.halt);
-                      self.run_cmd();
+                      },
+                      Err(e) => self.carry_on(Err(e))
                   }
             }
         }
     }
 
-    fn cmd_mode(&mut self, input: Input) {
-         let args = input.args;
-         if let Some(a) = args {
-             let args = a;
-             // Unwrap is safe because is_key is not None and there are args.
-             let cmd = input.cmd.unwrap();
-             let list_parent_path = self.list.parent_path.clone();
-             let mut path_cache = command_assistors::PathCache::new(
-                 list_parent_path.as_path()
-             );
-             path_cache.switch();
-             match cmd.as_str() {
-                 //"fzf" => {
-                 //    //Split cmd ('fzf')
-                 //    let split: Vec<&str> = input.as_read.split("fzf").collect();
-                 //    let cmd = split.iter().last().unwrap();
-                 //    let cmd = format!(r#"fzf {}"#, cmd);
-                 //    let output = terminal::shell::cmd(cmd.clone());
-                 //    let file_path = output.unwrap();
-                 //    terminal::shell::spawn("vim".to_string(), vec![file_path]);
-                 //},
-                 //"fzc" => {
-                 //    //let split: Vec<&str> = input.as_read.split("fzc").collect();
-                 //    let fzc_pathbuf = self.fzc_hook_path.as_ref().expect("fzc fail: no fzc hook path specified");
-                 //    let fzc_path_string = fzc_pathbuf.clone().into_os_string().into_string().unwrap();
-                 //    let output = terminal::shell::output("sh".to_string(), vec![fzc_path_string]).expect("fail to get output from fzc hook script");
-                 //    let cmd = String::from_utf8_lossy(&output.stdout).to_string();
-                 //    let mut input = Input::new();
-                 //    let input = input.parse(cmd);
-                 //    assert!(input.args.is_some());
-                 //    self.cmd_mode(input);
-                 //},
-                 _ => {
-                      terminal::shell::spawn(cmd.to_string(), args);
-                 }
-             }
-             path_cache.switch_back();
-         } else {
-             let as_read = input.as_read.as_str();
-             match as_read {
-                 "q" => (),
-                 "fzf" => {
-                     let mut path_cache = command_assistors::PathCache::new(
-                         self.list.parent_path.as_path()
-                     );
-                     path_cache.switch();
-                     let fzf_pathbuf = self.fzf_hook_path.as_ref().expect("fzf fail: no fzf hook path specified");
-                     let fzf_path_string = fzf_pathbuf.clone().into_os_string().into_string().unwrap();
-                     let file_path = terminal::shell::cmd(fzf_path_string).unwrap();
-                     //assert!(input.args.is_some());
-                     //let cmd_res = terminal::shell::cmd(cmd_path).unwrap();
-                     terminal::shell::spawn(self.default_editor.clone(), vec![file_path]);
-                     path_cache.switch_back();
-                 },
-                 "fzc" => {
-                     let mut path_cache = command_assistors::PathCache::new(
-                         self.list.parent_path.as_path()
-                     );
-                     path_cache.switch();
-                     let fzc_pathbuf = self.fzc_hook_path.as_ref().expect("fzc fail: no fzc hook path specified");
-                     let fzc_path_string = fzc_pathbuf.clone().into_os_string().into_string().unwrap();
-                     let cmd = terminal::shell::cmd(fzc_path_string).unwrap();
-                     let input = Input::new();
-                     let input = input.parse(cmd);
-                     //assert!(input.args.is_some());
-                     //let cmd_res = terminal::shell::cmd(cmd_path).unwrap();
-                     path_cache.switch_back();
-                     self.cmd_mode(input);
-                 },
-                 "fzd" => {
-                     let list_parent_path = self.list.parent_path.clone();
-                     let mut path_cache = command_assistors::PathCache::new(
-                         list_parent_path.as_path()
-                     );
-                     path_cache.switch();
-                     let fzd_pathbuf = self.fzd_hook_path.as_ref().expect("fzd fail: no fzd hook path specified");
-                     let fzd_path_string = fzd_pathbuf.clone().into_os_string().into_string().unwrap();
-                     let dir = terminal::shell::cmd(fzd_path_string).unwrap();
-                     let mut dir_pathbuf = PathBuf::from(dir);
-                     let mut pathbuf_vec: Vec<PathBuf> = vec![];
-                     if metadata(dir_pathbuf.clone()).unwrap().is_dir() {
-                         loop {
-                             if dir_pathbuf != PathBuf::from("") {
-                                 if dir_pathbuf != PathBuf::from("/") {
-                                     pathbuf_vec.push(dir_pathbuf.clone());
-                                 } else {
-                                     break
-                                 }
-                             } else {
-                                 break
-                             }
-                             dir_pathbuf.pop();
-                         }
-                         for dir_pathbuf in pathbuf_vec.iter().rev() {
-                             let list = self.list.clone().update(dir_pathbuf);
-                             self.update(list);
-                         }
-                         self.halt = false;
-                         let halt = self.list.filter.is_some();
-                         self.update_file_display(halt);
-                     }
-                     path_cache.switch_back();
-                 },
-                 "zsh" => {
-                     let mut path_cache = command_assistors::PathCache::new(
-                         self.list.parent_path.as_path()
-                     );
-                     path_cache.switch();
-                     //Split cmd ('zsh')
-                     //let split: Vec<&str> = input.as_read.split("zsh").collect();
-                     //let cmd = split.iter().last().unwrap();
-                     //let cmd = format!(r#"zsh {}"#, cmd);
-                     terminal::shell::spawn("zsh".to_string(), vec![]);
-                     path_cache.switch_back();
-                 },
-                 _ => {
-                     let mut path_cache = command_assistors::PathCache::new(
-                         self.list.parent_path.as_path()
-                     );
-                     path_cache.switch();
-                     let _output = terminal::shell::cmd(as_read.to_string()).unwrap();
-                     path_cache.switch_back();
-                 }
-             }
+    // Into list's dir, unless it can't be read; then nothing's changed.
+    fn go(&mut self, list: List) -> error::Result<()> {
+        let list = self.listed(list)?;
+        self.leave();
+        self.list = list;
+        self.record_visit();
+
+        Ok(())
+    }
+
+    // With the first matching opener rule, else the editor.
+    fn open_file(&self, file_pathbuf: PathBuf) -> error::Result<()> {
+        match opener::handlers(&self.open_rules, &file_pathbuf).first() {
+            Some(rule) => terminal::shell::open_with(&rule.cmd, &file_pathbuf, &self.default_editor).map_err(Error::spawn(&rule.cmd)),
+            None => terminal::shell::spawn(self.default_editor.clone(), vec![file_pathbuf]).map_err(Error::spawn(&self.default_editor))
         }
     }
 
-    fn open_file_by_key_mode(&mut self, input: Input, is_fuzzed: bool) {
-        let get_file = |key_string: String| {
-             let key: usize = key_string.parse().unwrap();
-             self.list.get_file_by_key(key, !is_fuzzed).unwrap()
+    // The right pane's grid, cut off at rows, and where its keys are in it.
+    // Laid out a page at a time, as a dir still streaming in can get big.
+    fn pane_display(&self, width: usize, rows: usize) -> (String, Vec<pages::Spot>) {
+        let mut pane = match self.pane.clone() {
+            Some(pane) => pane,
+            None => return (String::new(), vec![])
         };
+        let entries = pane.order_and_sort_list(true, false);
+        let keys: Vec<usize> = entries.iter().filter_map(|entry| entry.key).collect();
+        let labels = list::pane_key_entries(&pane, entries, &self.colors, "b");
+        let total = labels.len();
+        // A line is kept for saying how many more there are.
+        let shape = pages::Shape { width, rows: rows.saturating_sub(1).max(1), one_column: self.test };
+        let pages = pages::Pages::new(None, labels, keys, shape);
+        let mut display = pages.render(0);
+        let spots = pages.spots(0, &display);
+        let shown = pages.range(0).end;
+        if shown < total {
+            display.push_str(&format!("[{} more]\n", total - shown));
+        }
 
-        //let mut n = 0;
-        //let mut format_cmd = |key: PathBuf| {
-        //            n +=1;
-        //            let file_string = get_file(key.to_str().unwrap().to_string()).to_str().unwrap().to_string();
-        //            let file_string = file_string.replace(" ", r"\ ");
-        //           format!(r#"{}={}"#, n, file_string)
-        //};
-
-        if let Some (r) = input.args {
-            let _output_vec: Vec<String> =
-                r.iter()
-                    .map(|key|
-                         get_file(key.to_string())
-                    ).map(|file|
-                        terminal::shell::cmd(format!("{:?} {:?}", self.default_opener, file)).unwrap()
-                    ).collect();
-        } else {
-            ()
+        (display, spots)
+    }
+
+    fn pane_enter<P: AsRef<Path>>(&mut self, dir: P) -> error::Result<()> {
+        let mut list = List::new(dir.as_ref());
+        list.sort = self.list.sort;
+        list.du = self.list.du.clone();
+        self.pane = Some(self.listed(list)?);
+
+        Ok(())
+    }
+
+    // dual [key] opens the right pane (at the keyed dir or here), dual alone
+    // closes it. b<key> works in the right pane like <key> on the left, and
+    // cp/mv <a|b keys> send entries across to the other pane's dir.
+    fn pane_mode(&mut self, input: Input, is_fuzzed: bool) {
+        let cmd = input.cmd.clone().unwrap_or_default();
+        let args = input.args.clone().unwrap_or_default();
+        if let Some(('a', key)) = parse_pane_key(&cmd) {
+            return self.key_mode(self.list.clone(), key, is_fuzzed);
         }
+        let res = self.pane_cmd(&cmd, &args, is_fuzzed);
+        self.carry_on(res.map_err(Error::cmd(&cmd)));
+    }
 
-        self.halt = false;
-        let halt = self.list.filter.is_some();
-        self.update_file_display(halt);
-        if !halt {
-            self.run_cmd();
+    fn pane_cmd(&mut self, cmd: &str, args: &[String], is_fuzzed: bool) -> error::Result<()> {
+        let get_file = |list: &List, key: usize, is_fuzzed: bool| list.get_file_by_key(key, !is_fuzzed);
+
+        if let Some((_, key)) = parse_pane_key(cmd) {
+            let right = self.pane.clone().ok_or_else(no_pane)?;
+            if key == 0 {
+                let up = right.parent_path.parent().map(Path::to_path_buf).unwrap_or(right.parent_path.clone());
+                return self.pane_enter(up);
+            }
+            let path = get_file(&right, key, false)?;
+            return if path.is_dir() {
+                self.pane_enter(path)
+            } else {
+                self.open_file(path)
+            };
         }
+
+        match (cmd, args) {
+            ("dual", []) if self.pane.is_some() => {
+                self.pane = None;
+            },
+            ("dual", []) => {
+                self.pane_enter(self.list.parent_path.clone())?;
+            },
+            ("dual", [key]) => {
+                let key: usize = key.parse().map_err(|_| Error::BadArg(format!("'{}' isn't a key", key)))?;
+                let dir = self.file_by_key(key, is_fuzzed)?;
+                if !dir.is_dir() {
+                    return Err(Error::BadArg(format!("{} isn't a dir", dir.display())));
+                }
+                self.pane_enter(dir)?;
+            },
+            ("cp", keys) | ("mv", keys) if !keys.is_empty() => {
+                let right = self.pane.clone().ok_or_else(no_pane)?;
+                for key in keys {
+                    let (pane, n) = parse_pane_key(key).ok_or_else(|| Error::BadArg(format!("'{}' isn't a pane key like a3 or b7", key)))?;
+                    let (from, to) = if pane == 'a' {
+                        (self.file_by_key(n, is_fuzzed)?, right.parent_path.clone())
+                    } else {
+                        (get_file(&right, n, false)?, self.list.parent_path.clone())
+                    };
+                    let mut cmd_args = if cmd == "cp" { vec!["-r".to_string()] } else { vec![] };
+                    cmd_args.push("--".to_string());
+                    cmd_args.push(from.to_string_lossy().into_owned());
+                    cmd_args.push(to.to_string_lossy().into_owned());
+                    terminal::shell::spawn(cmd.to_string(), cmd_args).map_err(Error::spawn(cmd))?;
+                }
+                let (filter, page) = (self.list.filter.clone(), self.list.page);
+                self.enter(self.list.parent_path.clone())?;
+                self.list.filter = filter;
+                self.list.page = page;
+                self.pane_enter(right.parent_path)?;
+            },
+            _ => return Err(Error::Parse(commands::ParseError::Usage("dual [key] | b<key> | cp <a|b keys> | mv <a|b keys>")))
+        }
+
+        Ok(())
     }
 
-    fn key_related_mode(&mut self, input: Result<Option<String>, std::io::Error>, is_fuzzed: bool) {
-        match input {
-            Ok(t) =>  {
-                if let Some(i) = t {
-                    let input = Input::new();
-                    let input = input.parse(i);
-                    // Safe to unwrap.
-                    //
-                    match input.clone().cmd_type.unwrap() {
-                        CmdType::SingleKey => {
-                            self.key_mode(self.list.clone(), input, is_fuzzed);
-                        },
-                        CmdType::OpenKeys => {
-                            self.open_file_by_key_mode(input, is_fuzzed);
-                        },
-                        CmdType::MultipleKeys => {
-                            /*
-                                * get_file_by_key for each key
-                                * let text_vec = vec![r#"printf '1=file1; 2=file2;...'; \n "#]
-                                * then type_text_spawn(text_vec);
-                            */
-                            self.return_file_by_key_mode(input, is_fuzzed);
-                        },
-                        CmdType::FilterKeys => {
-                            self.filter_mode(self.list.clone());
-                        },
-                        _ => ()
+    fn cmd_mode(&mut self, input: Input, on_terminal: bool) {
+        if input.args.is_none() {
+            if let Some(run) = commands::shell_builtin(&input.as_read) {
+                return run(self, input, false);
+            }
+        }
+        if let Err(e) = self.shell_line(input, on_terminal) {
+            self.error = Some(e.to_string());
+        }
+    }
+
+    // On the terminal for c !<cmd>. Otherwise what it prints is caught and
+    // put up over the grid, like the help, so the screen stays put.
+    fn shell_line(&mut self, input: Input, on_terminal: bool) -> error::Result<()> {
+        let (cmd, args) = match (input.cmd, input.args) {
+            (Some(cmd), Some(args)) => (cmd, args),
+            _ => (input.as_read, vec![])
+        };
+        if on_terminal {
+            return self.in_dir(|| terminal::shell::spawn(cmd.clone(), args).map_err(Error::spawn(&cmd)));
+        }
+        let output = self.in_dir(|| terminal::shell::output(cmd.clone(), args).map_err(Error::spawn(&cmd)))?;
+        let lines: Vec<String> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .chain(String::from_utf8_lossy(&output.stderr).lines())
+            .map(|line| line.replace('\t', "    "))
+            .collect();
+        if !lines.is_empty() {
+            self.output = Some(lines);
+            self.help = Some(0);
+        }
+        match output.status.code() {
+            Some(0) => Ok(()),
+            Some(code) => Err(Error::Spawn(cmd.clone(), std::io::Error::other(format!("{} exit with {}", cmd, code)))),
+            None => Err(Error::Spawn(cmd.clone(), std::io::Error::other(format!("{} was killed", cmd))))
+        }
+    }
+
+    // Run from the listed dir, as shell lines and hooks expect.
+    fn in_dir<T, F: FnOnce() -> error::Result<T>>(&self, f: F) -> error::Result<T> {
+        let dir = self.list.parent_path.clone();
+        let mut path_cache = command_assistors::PathCache::new(dir.as_path());
+        path_cache.switch().map_err(Error::at(&dir))?;
+        let res = f();
+        path_cache.switch_back().map_err(|e| Error::Other(format!("couldn't go back to the dir lsk started in: {}", e)))?;
+
+        res
+    }
+
+    // The c built-ins run outside the grid, so failures wait on the status line.
+    fn report(&mut self, run: fn(&mut LsKey) -> error::Result<()>) {
+        if let Err(e) = run(self) {
+            self.error = Some(e.to_string());
+        }
+    }
+
+    // A hook's output.
+    fn hook(&self, name: &'static str, hook: Option<&PathBuf>) -> error::Result<String> {
+        let hook = hook.ok_or(Error::NoHook(name))?.to_string_lossy().into_owned();
+
+        self.in_dir(|| terminal::shell::cmd(hook.clone()).map_err(Error::spawn(hook)))
+    }
+
+    // c fzf
+    fn fuzzy_find(&mut self) -> error::Result<()> {
+        let file_path = self.hook("fuzzy_find", self.fzf_hook_path.as_ref())?;
+        let editor = self.default_editor.clone();
+
+        self.in_dir(|| terminal::shell::spawn(editor.clone(), vec![file_path]).map_err(Error::spawn(editor)))
+    }
+
+    // c fzc
+    fn fuzzy_cmd(&mut self) -> error::Result<()> {
+        let cmd = self.hook("fuzzy_cmd", self.fzc_hook_path.as_ref())?;
+        self.cmd_mode(Input::new().parse(cmd), true);
+
+        Ok(())
+    }
+
+    // c fzd
+    fn fuzzy_dir(&mut self) -> error::Result<()> {
+        let dir = self.hook("fuzzy_dir", self.fzd_hook_path.as_ref())?;
+        let mut dir_pathbuf = PathBuf::from(dir);
+        let mut pathbuf_vec: Vec<PathBuf> = vec![];
+        let full = self.list.parent_path.join(&dir_pathbuf);
+        if metadata(&full).map_err(Error::at(&full))?.is_dir() {
+            loop {
+                if !dir_pathbuf.as_os_str().is_empty() {
+                    if dir_pathbuf != Path::new("/") {
+                        pathbuf_vec.push(dir_pathbuf.clone());
+                    } else {
+                        break
                     }
                 } else {
-                    ()
+                    break
+                }
+                dir_pathbuf.pop();
+            }
+            // Each dir on the way goes in the path history.
+            let mut list = self.list.clone();
+            for dir_pathbuf in pathbuf_vec.iter().rev() {
+                list = list.update(dir_pathbuf);
+            }
+            self.go(list)?;
+            self.halt = false;
+            let halt = self.list.filter.is_some();
+            self.update_file_display(halt);
+        }
+
+        Ok(())
+    }
+
+    // c sh, c !
+    fn subshell(&mut self) -> error::Result<()> {
+        terminal::shell::subshell(self.list.parent_path.as_path()).map_err(Error::spawn("the shell"))?;
+        self.refresh()
+    }
+
+    fn open_file_by_key_mode(&mut self, input: Input, is_fuzzed: bool) {
+        let res = self.files_by_keys("o", &input.args.unwrap_or_default(), is_fuzzed)
+            .and_then(|files| self.open_files(&files));
+
+        self.carry_on(res);
+    }
+
+    // One file gets a menu when there's a choice; several get their first handler.
+    fn open_files(&self, files: &[PathBuf]) -> error::Result<()> {
+        let default = |file: &PathBuf| {
+            terminal::shell::cmd(format!("{:?} {:?}", self.default_opener, file))
+                .map(drop)
+                .map_err(Error::spawn(&self.default_opener))
+        };
+        let with = |rule: &opener::Rule, file: &PathBuf| {
+            terminal::shell::open_with(&rule.cmd, file, &self.default_editor).map_err(Error::spawn(&rule.cmd))
+        };
+
+        if let [file] = files {
+            let rules = opener::handlers(&self.open_rules, file);
+            if rules.is_empty() {
+                default(file)
+            } else if let Some(choice) = choose(
+                &format!("Open {} with:", file.display()),
+                rules.iter().map(|rule| rule.cmd.clone()).chain(Some(self.default_opener.clone())).collect()
+            ) {
+                match rules.get(choice) {
+                    Some(rule) => with(rule, file),
+                    None => default(file)
+                }
+            } else {
+                Ok(())
+            }
+        } else {
+            files.iter().try_for_each(|file| match opener::handlers(&self.open_rules, file).first() {
+                Some(rule) => with(rule, file),
+                None => default(file)
+            })
+        }
+    }
+
+    // tab [key] opens a tab, gt [n] switches (to the next without n), tabc
+    // closes, and cpt/mvt <n> <keys> copy/move entries into tab n's dir.
+    fn tab_mode(&mut self, input: Input, is_fuzzed: bool) {
+        let cmd = input.cmd.clone().unwrap_or_default();
+        let args = input.args.unwrap_or_default();
+        let res = self.tab_cmd(&cmd, &args, is_fuzzed);
+        self.carry_on(res.map_err(Error::cmd(&cmd)));
+    }
+
+    fn tab_cmd(&mut self, cmd: &str, args: &[String], is_fuzzed: bool) -> error::Result<()> {
+        let get_file = |ls_key: &LsKey, key: &str| -> error::Result<PathBuf> {
+            let key: usize = key.parse().map_err(|_| Error::BadArg(format!("'{}' isn't a key", key)))?;
+            ls_key.file_by_key(key, is_fuzzed)
+        };
+        let tab_number = |n: &str| -> error::Result<usize> {
+            match n.parse::<usize>() {
+                Ok(n) if n >= 1 => Ok(n - 1),
+                _ => Err(Error::BadArg(format!("'{}' isn't a tab", n)))
+            }
+        };
+
+        match (cmd, args) {
+            ("tab", []) | ("tab", [_]) => {
+                let dir = match args.first() {
+                    Some(key) => get_file(self, key)?,
+                    None => self.list.parent_path.clone()
+                };
+                if !dir.is_dir() {
+                    return Err(Error::BadArg(format!("{} isn't a dir", dir.display())));
                 }
+                let current = self.session();
+                self.enter(&dir)?;
+                self.history = Default::default();
+                self.fuzzy_query = None;
+                let new = self.session();
+                self.tabs.open(current, new);
+            },
+            ("gt", []) | ("gt", [_]) => {
+                let to = match args.first() {
+                    Some(n) => tab_number(n)?,
+                    None => (self.tabs.active() + 1) % self.tabs.count()
+                };
+                let session = self.tabs.switch(self.session(), to).map_err(Error::Missing)?;
+                self.load_session(session)?;
             },
-            Err(_) => ()
+            ("tabc", []) => {
+                let session = self.tabs.close().map_err(Error::Other)?;
+                self.load_session(session)?;
+            },
+            ("cpt", [n, keys @ ..]) | ("mvt", [n, keys @ ..]) if !keys.is_empty() => {
+                let n = tab_number(n)?;
+                let dest = self.tabs.dir(n, &self.list.parent_path).ok_or_else(|| Error::Missing(format!("no tab {}", n + 1)))?;
+                let mut paths = vec![];
+                for key in keys {
+                    paths.push(get_file(self, key)?.to_string_lossy().into_owned());
+                }
+                let (program, mut cmd_args) = if cmd == "cpt" {
+                    ("cp".to_string(), vec!["-r".to_string(), "--".to_string()])
+                } else {
+                    ("mv".to_string(), vec!["--".to_string()])
+                };
+                cmd_args.extend(paths);
+                cmd_args.push(dest.to_string_lossy().into_owned());
+                terminal::shell::spawn(program.clone(), cmd_args).map_err(Error::spawn(program))?;
+                let (filter, page) = (self.list.filter.clone(), self.list.page);
+                self.enter(self.list.parent_path.clone())?;
+                self.list.filter = filter;
+                self.list.page = page;
+            },
+            _ => return Err(Error::Parse(commands::ParseError::Usage("tab [key] | gt [n] | tabc | cpt <n> <keys> | mvt <n> <keys>")))
+        }
+
+        Ok(())
+    }
+
+    // - goes back, + goes forward.
+    fn history_mode(&mut self, input: Input) {
+        let here = self.location();
+        let to = if input.as_read == "-" {
+            self.history.back(here)
+        } else {
+            self.history.forward(here)
+        };
+        let res = match to {
+            Some(location) => self.restore(location),
+            None => Ok(())
+        };
+
+        self.carry_on(res);
+    }
+
+    // bm, bm <name>, bm add [name], bm rm <name>
+    fn bookmark_mode(&mut self, input: Input) {
+        let args = input.args.unwrap_or_default();
+        let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+        let res = bookmarks::Bookmarks::load().and_then(|mut bookmarks| {
+            match args.as_slice() {
+                [] => {
+                    let names: Vec<&String> = bookmarks.marks.keys().collect();
+                    let items = bookmarks.marks.iter().map(|(name, dir)| format!("{}  {}", name, dir.display())).collect();
+                    match choose("Bookmarks:", items) {
+                        Some(choice) => self.jump_to(bookmarks.marks[names[choice]].clone()),
+                        None => Ok(())
+                    }
+                },
+                ["add"] | ["add", _] => {
+                    bookmarks.add(args.get(1).copied(), &self.list.parent_path)?;
+                    bookmarks.save()
+                },
+                ["rm", name] => {
+                    if !bookmarks.remove(name) {
+                        return Err(Error::Missing(format!("no bookmark '{}'", name)));
+                    }
+                    bookmarks.save()
+                },
+                [name] => {
+                    let dir = bookmarks.get(name).cloned().ok_or_else(|| Error::Missing(format!("no bookmark '{}'", name)))?;
+                    self.jump_to(dir)
+                },
+                _ => Err(Error::Parse(commands::ParseError::Usage("bm [name] | bm add [name] | bm rm <name>")))
+            }
+        });
+
+        self.carry_on(res.map_err(Error::cmd("bm")));
+    }
+
+    // z <query> jumps to the best match, zi [query] (or a bare z) lists them by key.
+    fn frecency_mode(&mut self, input: Input) {
+        let jump = input.cmd.as_deref() == Some("z");
+        let query = input.args.unwrap_or_default().join(" ");
+        let res = frecency::Frecency::load().and_then(|mut frecency| {
+            if frecency.prune() {
+                frecency.save()?;
+            }
+            let now = frecency::now();
+            let here = self.list.parent_path.clone();
+            let hits: Vec<PathBuf> = frecency.query(&query, now)
+                .into_iter()
+                .map(|d| d.path.clone())
+                .filter(|path| *path != here)
+                .collect();
+
+            if jump && !query.is_empty() {
+                let best = hits.first().ok_or_else(|| Error::Missing(format!("no match for '{}'", query)))?;
+                self.jump_to(best)
+            } else {
+                let items = hits.iter().take(20).map(|path| path.display().to_string()).collect();
+                match choose("Dirs:", items) {
+                    Some(choice) => self.jump_to(&hits[choice]),
+                    None => Ok(())
+                }
+            }
+        });
+
+        self.carry_on(res.map_err(Error::cmd("z")));
+    }
+
+    fn key_related_mode(&mut self, input: Result<Option<String>, std::io::Error>, is_fuzzed: bool) {
+        if let Ok(Some(line)) = input {
+            match commands::parse(&line) {
+                Ok(parsed) => parsed.run(self, line, is_fuzzed),
+                Err(e) => self.carry_on(Err(e.into()))
+            }
+        }
+    }
+
+    // Where a mode ends up: what it did on screen, what went wrong on the
+    // status line, and on to the next line.
+    fn carry_on(&mut self, res: error::Result<()>) {
+        if let Err(e) = res {
+            self.error = Some(e.to_string());
+        }
+
+        self.halt = false;
+        let halt = self.list.filter.is_some();
+        self.update_file_display(halt);
+        if !halt {
+            self.run_cmd();
         }
     }
 
@@ -627,7 +1386,7 @@ impl LsKey {
         if self.test == true {
             if input.is_some() {
                 let hash = sha256(&input.clone().unwrap());
-                let original_dir = self.clone().list.path_history.into_iter().nth(0);
+                let original_dir = self.list.path_history.first().cloned();
                 if original_dir.is_some() {
                     let mut original_dir = original_dir.unwrap();
                     //file.write_all(stuff.as_bytes()).unwrap();
@@ -643,9 +1402,9 @@ impl LsKey {
                    }
                 }
             }
-            if self.display.is_some() {
-                let hash = sha256(&self.display.clone().unwrap().1);
-                let original_dir = self.clone().list.path_history.into_iter().nth(0);
+            if let Some(display) = &self.display {
+                let hash = sha256(&display.1);
+                let original_dir = self.list.path_history.first().cloned();
 
                 if original_dir.is_some() {
                     let mut original_dir = original_dir.unwrap();
@@ -667,33 +1426,163 @@ impl LsKey {
 
     fn read_process_chars(&mut self) -> (Option<String>, bool) {
         self.input = Input::new();
-        let stdin = stdin();
         let stdout = stdout();
         let stdout = stdout.lock().into_raw_mode().unwrap();
         let mut screen: AlternateScreen<RawTerminal<StdoutLock>> = AlternateScreen::from(stdout);
-        let stdin = stdin.lock();
+        let mut events = terminal::tty::Stdin.events();
         let mut result: Option<String> =  None;
         let mut is_fuzzed = false;
-        let orig_ls_key = self.clone();
+        let mut chosen_dir: Option<PathBuf> = None;
+        // Retyped as if from the keyboard, e.g. a fuzzy query restored by `-`.
+        let mut pending = self.pending_prompt.take().unwrap_or_default().chars().map(Key::Char).collect::<Vec<Key>>().into_iter();
+        let mut measured = self.measured();
+        let mut cleared = Cleared::of(self);
+        terminal::tty::catch_resize();
 
         clear_display(&mut screen);
+        if self.mouse {
+            write!(screen, "{}", terminal::tty::MOUSE_ON).unwrap();
+        }
 
         let input_string: String = self.input.display.iter().collect();
         self.test_data_update(Some(input_string));
-        display_files(self.clone(), b"", &mut screen, (0, 3));
+        display_files(self, b"", &mut screen, (0, 3));
 
-        for c in stdin.keys() {
+        loop {
+            let event = match pending.next() {
+                Some(key) => Ok(Event::Key(key)),
+                None if terminal::tty::ready(TICK) => match events.next() {
+                    Some(event) => event,
+                    None => break
+                },
+                None => {
+                    // Nothing typed; show entries and dir sizes that came in meanwhile.
+                    // While searching, they go to the full list backspacing restores,
+                    // and to the matcher so the search takes them in.
+                    if let Some(list) = self.pre_fuzz_list.as_mut().filter(|_| is_fuzzed) {
+                        let seen = list.files.len();
+                        if list.catch_up() {
+                            self.matcher.extend(list.files[seen..].to_vec());
+                            self.matcher.wait(FUZZY_WAIT);
+                        }
+                    }
+                    if is_fuzzed && self.fuzzy_matched() {
+                        display_files(self, b"", &mut screen, (0, 3));
+                    }
+                    if terminal::tty::resized() {
+                        self.update_file_display(self.list.filter.is_some());
+                        let input_string: String = self.input.display.iter().collect();
+                        clear_display(&mut screen);
+                        display_input(input_string, &mut screen, (0, 1));
+                        display_files(self, b"", &mut screen, (0, 3));
+                    }
+                    // Matches are left be; the change is picked up once the search is over.
+                    if !is_fuzzed && self.dir_changed() {
+                        self.relist();
+                        display_files(self, b"", &mut screen, (0, 3));
+                    }
+                    let sized = self.measured() != measured;
+                    measured = self.measured();
+                    // The right pane streams in too. Not while a key's being typed,
+                    // which the rest of the dir could move.
+                    let typing = !self.input.display.is_empty();
+                    let paned = !typing && self.pane.as_mut().is_some_and(List::catch_up);
+                    if (!is_fuzzed && !typing && self.list.catch_up()) || sized || paned {
+                        self.update_file_display(self.list.filter.is_some());
+                        display_files(self, b"", &mut screen, (0, 3));
+                    }
+                    continue;
+                }
+            };
+            // A click types the key clicked on, b<key> in the right pane. A right-click
+            // marks it on the left, and the wheel pages.
+            let c = match event.unwrap() {
+                Event::Key(c) => c,
+                Event::Mouse(MouseEvent::Press(button, x, y)) if self.mouse => {
+                    // The grid's hidden under the help, so only the wheel does anything.
+                    if self.help.is_some() {
+                        match button {
+                            MouseButton::WheelDown => self.turn_help(1),
+                            MouseButton::WheelUp => self.turn_help(-1),
+                            _ => self.help = None
+                        }
+                        redraw(self, &mut screen);
+                        continue;
+                    }
+                    match (button, self.key_at(x, y)) {
+                        (MouseButton::Left, Some((pane, key))) if self.input.display.is_empty() => {
+                            let typed = if pane == 'b' { format!("b{}\n", key) } else { format!("{}\n", key) };
+                            pending = typed.chars().map(Key::Char).collect::<Vec<Key>>().into_iter();
+                            continue;
+                        },
+                        (MouseButton::Right, Some(('a', key))) => {
+                            self.toggle_mark(key);
+                            cleared.marks = self.marks.clone();
+                        },
+                        (MouseButton::WheelDown, _) => self.turn_page(1),
+                        (MouseButton::WheelUp, _) => self.turn_page(-1),
+                        _ => continue
+                    }
+                    if self.input.display.is_empty() {
+                        cleared = Cleared::of(self);
+                    }
+                    self.notice = None;
+                    self.error = None;
+                    redraw(self, &mut screen);
+                    continue;
+                },
+                _ => continue
+            };
             self.input.full_backspace;
             clear_display(&mut screen);
-            let c = c.unwrap();
-
-            self.input.match_event(c);
+            self.notice = None;
+            self.error = None;
+
+            // A binding stands in for typing its line and hitting enter.
+            let binding = self.keymap.binding(c, self.input.display.is_empty()).map(String::from);
+            // Unless bound to something else. Space only pages at an empty prompt.
+            let turn = match c {
+                Key::PageDown => Some(1),
+                Key::PageUp => Some(-1),
+                Key::Char(' ') if self.input.display.is_empty() => Some(1),
+                _ => None
+            };
+            if let (Some(_), Some(by)) = (self.help, turn) {
+                self.turn_help(by);
+                redraw(self, &mut screen);
+                continue;
+            }
+            self.help = None;
+            if let (None, Some(by)) = (&binding, turn) {
+                self.turn_page(by);
+                if self.input.display.is_empty() {
+                    cleared = Cleared::of(self);
+                }
+                redraw(self, &mut screen);
+                continue;
+            }
+            match binding {
+                Some(line) => {
+                    self.input.display = line.chars().collect();
+                    self.input.display.push('\n');
+                },
+                None if matches!(c, Key::Char(_) | Key::Backspace) => self.input.match_event(c),
+                None => {
+                    self.notice = Some(format!("{} isn't bound, see [keys] in the config", keymap::key_name(c)));
+                    redraw(self, &mut screen);
+                    continue;
+                }
+            }
+            if self.input.display.last() == Some(&'\n') {
+                let line: String = self.input.display.iter().collect();
+                if let Some(expanded) = self.keymap.expand(line.trim_end_matches('\n')) {
+                    self.input.display = expanded.chars().collect();
+                    self.input.display.push('\n');
+                }
+            }
             let mut input_string: String = self.input.display.iter().collect();
-            let input = self.input.clone();
-            let first = input.display.iter().nth(0);
-            let input = self.input.clone();
-            let last = input.display.iter().last();
-            let mut _input = self.input.clone();
+            let first = self.input.display.first().copied();
+            let last = self.input.display.last().copied();
 
             let place = (0, 1);
             if let Some(_) = first {
@@ -704,7 +1593,7 @@ impl LsKey {
                         self.list = x;
                     }
                     if self.input.full_backspace {
-                       *self = orig_ls_key.clone();
+                       cleared.restore(self, is_fuzzed);
                        is_fuzzed = false;
                     }
                 }
@@ -715,9 +1604,28 @@ impl LsKey {
 
                 if let Some(mode) = some_mode {
                     match mode {
-                        Mode::Cmd(_) => {
-                             if &last == &Some(&'\n') {
-                                 self.cmd_read();
+                        Mode::Cmd(line) => {
+                             if last == Some('\n') {
+                                 // c sh, the hooks and c !<cmd> hand the terminal to another
+                                 // program; the rest run with their output caught, off screen.
+                                 let handover = shell_on_terminal(&line).is_some()
+                                     || commands::shell_builtin(line.trim_end_matches('\n')).is_some();
+                                 if handover {
+                                     if self.mouse {
+                                         write!(screen, "{}", terminal::tty::MOUSE_OFF).unwrap();
+                                     }
+                                     write!(screen, "{}", termion::screen::ToMainScreen).unwrap();
+                                     screen.flush().unwrap();
+                                     screen.suspend_raw_mode().unwrap();
+                                 }
+                                 self.cmd_read(line);
+                                 if handover {
+                                     screen.activate_raw_mode().unwrap();
+                                     write!(screen, "{}", termion::screen::ToAlternateScreen).unwrap();
+                                     if self.mouse {
+                                         write!(screen, "{}", terminal::tty::MOUSE_ON).unwrap();
+                                     }
+                                 }
 
                                  //Clear the command from the lsk console after executing.
                                  input_string = "".to_string();
@@ -726,11 +1634,8 @@ impl LsKey {
                              }
                         },
                         Mode::Work => {
-                             if &last == &Some(&'\n') {
-                                 let path = self.list.parent_path.clone();
-                                 let path = path.to_str().unwrap();
-                                 let cmd = format!(r#""$(printf 'cd {} \n ')""#, path).to_string();
-                                 terminal::parent_shell::type_text(cmd, 0);
+                             if last == Some('\n') {
+                                 chosen_dir = Some(self.list.parent_path.clone());
                                  self.is_fuzzed = false;
                                  break
                             } else {
@@ -740,8 +1645,8 @@ impl LsKey {
                         Mode::Fuzzy(fuzzy_mode_input) => {
                             if !is_fuzzed {
                                 self.pre_fuzz_list = Some(self.list.clone());
+                                self.matcher.start(self.list.files.clone());
                             }
-                            let _show = self.display.clone();
                             let some_keys = parse_keys(fuzzy_mode_input.as_str());
 
                             if let Some(keys) = some_keys {
@@ -757,7 +1662,8 @@ impl LsKey {
                                 }
 
                                 if self.input.display.iter().last() != Some(&'\n') {
-                                    self.fuzzy_update(fuzzy_mode_input);
+                                    self.fuzzy_query = fuzzy_mode_input.split(' ').next().filter(|query| !query.is_empty()).map(String::from);
+                                    self.fuzzy_search(&fuzzy_mode_input);
                                 }
                             }
 
@@ -780,11 +1686,11 @@ impl LsKey {
 
 
             if self.input.full_backspace {
-               *self = orig_ls_key.clone();
+               cleared.restore(self, is_fuzzed);
                is_fuzzed = false;
             }
             self.test_data_update(Some(input_string.clone()));
-            display_files(self.clone(), b"", &mut screen, (0, 3));
+            display_files(self, b"", &mut screen, (0, 3));
 
             if self.input.display.iter().last() == Some(&'\n') {
                 self.input.display.pop();
@@ -792,63 +1698,112 @@ impl LsKey {
                 result = Some(input_string);
                 self.is_fuzzed = is_fuzzed;
                 if self.is_fuzzed {
+                    // Keys pick from the full set of matches.
+                    self.matcher.finish();
+                    self.fuzzy_matched();
                 }
                 break
             }
         }
 
+        if self.mouse {
+            write!(screen, "{}", terminal::tty::MOUSE_OFF).unwrap();
+        }
         write!(screen, "{}", termion::cursor::Show).unwrap();
+        drop(screen);
+
+        if let Some(dir) = chosen_dir {
+            if self.shell_output.pick {
+                self.pick(vec![dir]);
+            } else if let Err(e) = self.shell_output.choose_dir(dir) {
+                eprintln!("Couldn't write chosen dir: {}", e);
+            }
+        }
 
         (result, self.input.execute)
     }
 
-    pub fn mode_parse(&mut self, mut input: String) -> Option<Mode> {
-        let len = input.len();
-        let mode = if len >= 2 {
-             let mode = input.as_str();
-             if mode == "w\n" {
-                 Some(Mode::Work)
-             } else {
-                  let mode: String = input.drain(..2).collect();
-                  let mode = mode.as_str();
-                  match mode {
-                      "s " => Some(Mode::Fuzzy(input.clone())),
-                      "c " => Some(Mode::Cmd(input.clone())),
-                      _ => None
-                  }
-             }
-        } else {
-            None
+    pub fn mode_parse(&mut self, input: String) -> Option<Mode> {
+        // s and c take over the prompt once the space is typed, w once it's entered.
+        let (word, rest) = match input.split_once(' ') {
+            Some((word, rest)) => (word, Some(rest)),
+            None => (input.strip_suffix('\n')?, None)
         };
 
-        mode
+        match (commands::prompt(word)?, rest) {
+            (commands::Prompt::Fuzzy, Some(rest)) => Some(Mode::Fuzzy(rest.to_string())),
+            (commands::Prompt::Shell, Some(rest)) => Some(Mode::Cmd(rest.to_string())),
+            (commands::Prompt::Work, None) => Some(Mode::Work),
+            _ => None
+        }
     }
 
-    fn cmd_read(&mut self) -> String {
+    // The c line, as mode_parse took it from the prompt.
+    fn cmd_read(&mut self, line: String) {
          self.input.display.pop();
-         let input_string: String = self.input.display.iter().collect();
-         let cmd_mode = self.mode_parse(input_string.clone()).unwrap(); //safe
-
-         match cmd_mode {
-             Mode::Cmd(cmd_mode_input) => {
-                 let input = Input::new();
-                 let input = input.parse(cmd_mode_input);
-
-                 match input.clone().cmd_type.unwrap() {
-                     CmdType::Cmd => {
-                         self.cmd_mode(input);
-                     },
-                     _ => {}
-                 }
-                 //break
-             }
-             _ => { }
+         let line = line.trim_end_matches('\n').to_string();
+         let (on_terminal, line) = match shell_on_terminal(&line) {
+             Some(rest) => (true, rest.to_string()),
+             None => (false, line)
+         };
+         let input = Input::new().parse(line);
+         if input.cmd_type == Some(CmdType::Cmd) {
+             self.cmd_mode(input, on_terminal);
          }
+    }
+}
+
+
+// What backspacing the prompt clear puts back: the listing as it was
+// when the prompt was last empty, before any search narrowed it.
+struct Cleared {
+    filter: Option<Vec<usize>>,
+    page: usize,
+    marks: BTreeSet<PathBuf>,
+    fuzzy_query: Option<String>,
+}
+
+impl Cleared {
+    fn of(ls_key: &LsKey) -> Self {
+        Cleared {
+            filter: ls_key.list.filter.clone(),
+            page: ls_key.list.page,
+            marks: ls_key.marks.clone(),
+            fuzzy_query: ls_key.fuzzy_query.clone(),
+        }
+    }
 
-        input_string
+    // The searched list went to pre_fuzz_list, still streaming in meanwhile.
+    fn restore(&self, ls_key: &mut LsKey, searched: bool) {
+        if searched {
+            if let Some(list) = ls_key.pre_fuzz_list.take() {
+                ls_key.list = list;
+            }
+            ls_key.fuzzy_list = None;
+        }
+        ls_key.input = Input::new();
+        ls_key.list.filter = self.filter.clone();
+        ls_key.list.page = self.page;
+        ls_key.marks = self.marks.clone();
+        ls_key.fuzzy_query = self.fuzzy_query.clone();
+        ls_key.update_file_display(ls_key.list.filter.is_some());
     }
 }
 
+// Runs outside the alternate screen, so a plain keyed prompt is enough.
+// Anything but a listed key cancels.
+fn choose(title: &str, items: Vec<String>) -> Option<usize> {
+    println!("{}", title);
+    for (n, item) in items.iter().enumerate() {
+        println!("  {} {}", n + 1, item);
+    }
+
+    let choice = terminal::input_n_display::read().ok()??;
+    match choice.trim().parse::<usize>() {
+        Ok(n) if n >= 1 && n <= items.len() => Some(n - 1),
+        _ => None
+    }
+}
 
 fn clear_display(screen: &mut AlternateScreen<RawTerminal<StdoutLock>>) {
     write!(
@@ -859,6 +1814,14 @@ fn clear_display(screen: &mut AlternateScreen<RawTerminal<StdoutLock>>) {
     screen.flush().unwrap();
 }
 
+// Everything over again: the prompt as typed so far, then the grid.
+fn redraw(ls_key: &LsKey, screen: &mut AlternateScreen<RawTerminal<StdoutLock>>) {
+    let input_string: String = ls_key.input.display.iter().collect();
+    clear_display(screen);
+    display_input(input_string, screen, (0, 1));
+    display_files(ls_key, b"", screen, (0, 3));
+}
+
 fn display_input(input_string: String, screen: &mut AlternateScreen<RawTerminal<StdoutLock>>, position: (u16, u16)) {
     write!(screen,
         "{}{}{}{}", format!("{}", input_string.as_str()
@@ -870,12 +1833,25 @@ fn display_input(input_string: String, screen: &mut AlternateScreen<RawTerminal<
     screen.flush().unwrap();
 }
 
-fn display_files(ls_key: LsKey, some_stuff: &[u8], screen: &mut AlternateScreen<RawTerminal<StdoutLock>>, position: (u16, u16)) {
-     let show = ls_key.clone().display;
-     if let Some(x) = show {
+fn display_files(ls_key: &LsKey, some_stuff: &[u8], screen: &mut AlternateScreen<RawTerminal<StdoutLock>>, position: (u16, u16)) {
+     if let Some(x) = &ls_key.display {
          if x.0 == ls_key.list.parent_path {
+              // The help, while it's up, hides the grid.
+              let shown = match ls_key.help {
+                  Some(page) => {
+                      let pages = ls_key.help_pages();
+                      let mut help = pages.render(page);
+                      if page + 1 < pages.count() {
+                          help.push_str(&format!("page {}/{}, pgdn for more\n", page + 1, pages.count()));
+                      } else if pages.count() > 1 {
+                          help.push_str(&format!("page {}/{}\n", page + 1, pages.count()));
+                      }
+                      help
+                  },
+                  None => x.1.clone()
+              };
               //into_raw_mode requires carriage returns.
-              let display = str::replace(x.1.as_str(), "\n", "\n\r");
+              let display = str::replace(shown.as_str(), "\n", "\n\r");
               write!(
                   screen,
                   "{}{}{}\n", format!("{}", std::str::from_utf8(&some_stuff).unwrap()),
@@ -894,6 +1870,45 @@ fn display_files(ls_key: LsKey, some_stuff: &[u8], screen: &mut AlternateScreen<
               ).unwrap();
               screen.flush().unwrap();
 
+              // An error outranks a notice for the one row they share.
+              let status = match (&ls_key.error, &ls_key.notice) {
+                  (Some(error), _) => Some(Colour::Red.bold().paint(error)),
+                  (None, Some(notice)) => Some(Style::new().bold().paint(notice)),
+                  (None, None) => None
+              };
+              if let Some(status) = status {
+                  write!(
+                      screen,
+                      "{}{}{}",
+                      termion::cursor::Goto(1, 1),
+                      status,
+                      termion::clear::UntilNewline,
+                  ).unwrap();
+              }
+
+              // The tab bar sits just above the input line.
+              if let Some(bar) = ls_key.tabs.bar(&ls_key.list.parent_path) {
+                  write!(
+                      screen,
+                      "{}{}{}",
+                      termion::cursor::Goto(1, 2),
+                      bar,
+                      termion::clear::UntilNewline,
+                  ).unwrap();
+              }
+
+              // The status bar takes the bottom row, cut to fit.
+              if let Some(bar) = &ls_key.status_bar {
+                  let (columns, height) = termion::terminal_size().unwrap_or((80, 24));
+                  let bar: String = bar.chars().take(usize::from(columns)).collect();
+                  write!(
+                      screen,
+                      "{}{}",
+                      termion::cursor::Goto(1, height),
+                      Style::new().reverse().paint(format!("{:1$}", bar, usize::from(columns))),
+                  ).unwrap();
+              }
+
               write!(
                   screen,
                   "{}",
@@ -946,13 +1961,6 @@ impl Input {
                         }
                     }
                 }
-                Key::Alt(c) => println!("^{}", c),
-                Key::Ctrl(c) => println!("*{}", c),
-                Key::Esc => println!("ESC"),
-                Key::Left => println!("←"),
-                Key::Right => println!("→"),
-                Key::Up => println!("↑"),
-                Key::Down => println!("↓"),
                 Key::Backspace => {
                     self.unwiddle = true;
                     if self.display.pop().is_some() {
@@ -985,111 +1993,21 @@ impl Input {
     }
 
     pub fn parse(mut self, input: String) -> Self {
-        let (cmd, args) = self.parse_cmd(input.clone());
-        let is_key = if args == None {
-            let key: Result<usize, std::num::ParseIntError> = cmd.clone().unwrap().parse();
-            match key {
-                Ok(_) => Some(true),
-                Err(_) => Some(false)
-            }
-        } else {
-            Some(false)
-        };
-
-        let is_filter = {
-            let mut res = false;
-            let mut input_vec_str: Vec<&str> = input.split("-").collect();
-
-            // Make sure it's more than one item (it's range of values).
-            if input_vec_str.iter().count() > 1 {
-                res = true;
-            }
-
-            if res {
-                // Does it end in "-"?
-                let last = input_vec_str.iter().last();
-                let mut open_range = false;
-                if let Some(l) = last {
-                    if l == &"" {
-                        open_range = true;
-                   }
-                }
-
-                // Only want to deal with integers if it's an open range.
-                if open_range {
-                   input_vec_str.pop();
-                }
-
-                // Make sure it'sall integers.
-                for i in input_vec_str.into_iter() {
-                    let key: Result<usize, std::num::ParseIntError> = i.parse();
-                    if !key.is_ok() {
-                        res = false;
-                        break;
-                    } else {
-                        res = true;
-                    }
-                }
-            } // if res
-
-            res
-        };
-
-
-        let are_all_keys = if let Some(c) = cmd.clone() {
-             if c == "r".to_string() {
-                 let _args = args.clone();
-                 if let Some(a) = _args {
-                      self.are_all_keys(a)
-                 }
-                 else {
-                     false
-                 }
-             } else {
-                 false
-             }
-        } else {
-            false
-        };
-
-        let are_all_keys_open = if let Some(c) = cmd.clone() {
-             if c == "o".to_string() {
-                 let _args = args.clone();
-                 if let Some(a) = _args {
-                      self.are_all_keys(a)
-                 }
-                 else {
-                     false
-                 }
-             } else {
-                 false
-             }
-        } else {
-            false
+        let (cmd, mut args) = self.parse_cmd(input.clone());
+        let command = commands::parse(&input).map(|parsed| parsed.command.syntax);
+        let cmd_type = match command {
+            Ok(commands::Syntax::Key) => CmdType::SingleKey,
+            Ok(commands::Syntax::Range) | Ok(commands::Syntax::From) => CmdType::FilterKeys,
+            Ok(commands::Syntax::Word(["o"])) => CmdType::OpenKeys,
+            Ok(commands::Syntax::Word(["r"])) if args.is_some() => CmdType::MultipleKeys,
+            _ => CmdType::Cmd
         };
-
-        let cmd_type = if are_all_keys {
-            CmdType::MultipleKeys
-        } else if are_all_keys_open {
-            CmdType::OpenKeys
-        } else if is_filter {
-            CmdType::FilterKeys
-        } else if let Some(k) = is_key {
-            if k {
-                CmdType::SingleKey
-            } else {
-                CmdType::Cmd
-            }
-        } else if is_filter {
-            CmdType::FilterKeys
-        } else {
-            CmdType::Cmd
-        };
-
-            //if cmd_type == CmdType::FilterKeys {
-            //    let few_ms = std::time::Duration::from_millis(1000);
-            //    std::thread::sleep(few_ms);
-            //}
+        // Built-ins skip the empty args that extra spaces leave, as the registry does.
+        if command.is_ok() {
+            args = args
+                .map(|args| args.into_iter().filter(|arg| !arg.is_empty()).collect::<Vec<String>>())
+                .filter(|args| !args.is_empty());
+        }
 
         self.cmd = cmd;
         self.args = args;
@@ -1107,19 +2025,6 @@ impl Input {
         (Some(cmd), args)
      }
 
-     fn are_all_keys(&self, input: Vec<String>) -> bool {
-        let is_num = |x: &str| {
-            let res: Result<usize, std::num::ParseIntError> = x.parse();
-            match res {
-                Ok(_) => true,
-                Err(_) => false
-            }
-        };
-        let is_all_nums = !input.iter().any(|x| !is_num(x.as_str()));
-
-        is_all_nums
-     }
-
      //fn is_key(&self, input: &Vec<String>) -> bool {
      //   if input.iter().count() == 1 {
      //       let key: Result<usize, std::num::ParseIntError> = input.iter().next().unwrap().parse();
@@ -1140,6 +2045,39 @@ pub enum Mode {
     Work,
 }
 
+// Columns between the panes in dual-pane mode.
+const PANE_GAP: usize = 3;
+// How long a keystroke waits on the fuzzy matcher before showing what it has.
+const FUZZY_WAIT: std::time::Duration = std::time::Duration::from_millis(50);
+// The screen row the grid starts on, under the notice, tab bar and prompt.
+const GRID_ROW: usize = 4;
+// How often the prompt looks up from the keyboard to redraw.
+const TICK: std::time::Duration = std::time::Duration::from_millis(100);
+
+// The <cmd> of c !<cmd>, which runs on the terminal. A bare c ! is the
+// subshell, so it isn't one.
+fn shell_on_terminal(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix('!')?.trim_start();
+
+    if rest.trim().is_empty() { None } else { Some(rest) }
+}
+
+/// a3 or b7: a key in the left or right pane.
+pub fn parse_pane_key(word: &str) -> Option<(char, usize)> {
+    let mut chars = word.chars();
+    let pane = chars.next().filter(|pane| *pane == 'a' || *pane == 'b')?;
+    let key = chars.as_str();
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    key.parse().ok().map(|key| (pane, key))
+}
+
+fn no_pane() -> Error {
+    Error::Missing("no right pane, open one with dual".to_string())
+}
+
 fn parse_keys(input: &str) -> Option<String> {
     let x = input;
     let mut y: Vec<&str> = x.split(" ").collect();
@@ -1274,7 +2212,7 @@ mod app_test {
 
                 let mut path_cache = command_assistors::PathCache::new(&path_test);
                 // Changing directories.
-                path_cache.switch();
+                path_cache.switch().unwrap();
 
                 println!("");
                 let text_vec = vec![
@@ -1302,10 +2240,13 @@ mod app_test {
                 }
 
                 let spawn = super::terminal::parent_shell::type_text_spawn(text_vec, $delay);
-                let fzf = PathBuf::from("/home/me/.fzf.sh");
-                let fzc = PathBuf::from("/home/me/.fzc.sh");
-                let fzd = PathBuf::from("/home/me/.fzd.sh");
-                let _ls_key = super::app::run(test_path_string.clone(), $list_all_bool, true, Some(fzf), Some(fzc), Some(fzd));
+                let mut config = Config::default();
+                config.apply_env();
+                config.all = $list_all_bool;
+                config.hooks.fuzzy_find = Some(PathBuf::from("/home/me/.fzf.sh"));
+                config.hooks.fuzzy_cmd = Some(PathBuf::from("/home/me/.fzc.sh"));
+                config.hooks.fuzzy_dir = Some(PathBuf::from("/home/me/.fzd.sh"));
+                let _ls_key = super::app::run(test_path_string.clone(), true, config, Default::default());
                 spawn.join().expect("failed to spawn thread");
 
                 let mut test_output_path = path_path.clone();
@@ -1341,7 +2282,7 @@ mod app_test {
                     Err(..) => assert!(false)
                 }
 
-                path_cache.switch_back();
+                path_cache.switch_back().unwrap();
 
                 std::fs::remove_file(output_mv_to_path_string).unwrap();
             }
@@ -1363,7 +2304,7 @@ mod app_test {
           "",                //$input7
           "macro_fzc_enter_file",
           ">Run lsk\n>Open file using vim with fzc hook\n>Quite vim\n>Quite lsk",
-          "64e1b450ebdc532da6ebd5b11df4347d67b48405aa166b05800180e1a1136bf2",
+          "42a8708efff9ce5bf7eea3713186128b67be326a9b04389a6e33265cc9c0bd4a",
           ignore/*macro_use*/
     );
 
@@ -1382,7 +2323,7 @@ mod app_test {
           "",                //$input7
           "macro_enter_file",
           ">Run lsk\n>Open file by key (2)\n>Quite vim\n>Quite lsk",
-         "dab8010a2c67e6986aa511180b41132058be77ad72a9c9c5fc076e371dccf584",
+         "cfb88e44319e6fc629ee56959de53c7a148edb6fe5bc8670b42738614dcc9427",
          ignore/*host_term_size_dependent*/
     );
 
@@ -1401,7 +2342,7 @@ mod app_test {
           "",                //$input7
           "macro_enter_file",
           ">Run lsk\n>Open file by key (2)\n>Quite vim\n>Quite lsk",
-          "99150c8a4c5960ee34cfbbb8393a65a9fe1c544394c1d02bf6a0a5cf0ad9b6a9",
+          "f8d86c619d1cf5ff23b71c5927c3df8cc811dee75c24a462a1e63277476c159d",
           ignore/*macro_use*/
     );
 
@@ -1420,7 +2361,7 @@ mod app_test {
           "",                //$input7
           "macro_enter_file_list_all",
           ">Run lsk\n>Open hidden file by key (2)\n>Quite vim\n>Quite lsk",
-          "e539e2c5a37d677c59fd71ca1739ae398ed467fc9dd506ec2512533f5d070ae4",
+          "a3e4b28308c63968c48dab759f871af96ee96e65b487a61c9e82127cdf98cae5",
           ignore/*macro_use*/
     );
 
@@ -1439,7 +2380,7 @@ mod app_test {
           "",
           "macro_fuzzy_enter_file",
           ">Run lsk\n>Fuzzy widdle\n>Open file by key (1)\n>Quite vim\n>Quite lsk",
-          "d7e51ab94b12bcb1773b2a92d0b4dec550c38f845add73850347a8bd03cc8f49",
+          "ed7457e6b7e76cf501532099c8e818079e3b27e09341eb7fdc035b098ca89336",
           ignore/*macro_use*/
     );
 
@@ -1458,7 +2399,7 @@ mod app_test {
           "",
           "macro_fuzzy_enter_dir",
           ">Run lsk\n>Fuzzy widdle\n>Open dir by key (1)\n>Quite vim\n>Quite lsk",
-          "9127fec0401b363161da1931c49795066cb21a98fd025be9816655019be48b2f",
+          "cd25f98f48973a30fa2b7a4c5ebfcffce0c3a622e940213d2a57c249502aeea0",
           ignore/*macro_use*/
     );
 
@@ -1477,7 +2418,7 @@ mod app_test {
           "",
           "macro_fuzzy_enter_dir",
           ">Run lsk\n>Fuzzy widdle\n>Open dir by key (1)\n>Go back (0) and repeat\n>Quite vim\n>Quite lsk",
-          "27a1709e1aaa369079a83d411e082c22bccca5987b008d71865e091864264e9f",
+          "663c0b87e40ba4d6e966c07e02fa1be05a8b7a1cbf5adcd8f85a2b763c8c7a71",
           ignore/*macro_use*/
     );
 
@@ -1496,7 +2437,7 @@ mod app_test {
           "",
           "macro_go_back_fuzzy_enter_back_into_dir",
           ">Run lsk\n>Go back (0)\n>Fuzzy widdle\n>Open back into original dir by key (2)\n>\n>Quite lsk",
-          "399976731f562f608d1d4c04afc1fa4f8f3530acf8683c39917d8771afebe5d1",
+          "7790ae59ce467bdc8df4827018b9041840e821cd34d1c28a074d27f596c33eee",
           ignore/*macro_use*/
     );
 
@@ -1534,7 +2475,7 @@ mod app_test {
            "q\r",
            "macro_fuzzy_backspace",
            ">Run lsk\n>OFuzzy widdle (2)\n>Backspace fully (bad behavior)\n>Quite lsk",
-           "ee86f4888a18c074a2df32a467ee94027ea5f437d0fede8125e3526e07ee8147",
+           "5a51adec6b0947db5c8a24ffd6cca1c5381269a4be7e0908ddbf5d22bf78993d",
            ignore/*macro_use*/
      );
 
@@ -1553,7 +2494,7 @@ mod app_test {
            "q\r",
            "macro_bad_fuzzy_backspace_enter",
            ">Run lsk\n>OFuzzy widdle (2)\n>Backspace partially (bad behavior)\n>Quite lsk",
-           "83f5005ed89aca8834f69b3e064afca283c3ec6f90000e6e902473e1a582d11c",
+           "be6086e7acd091a5c1f3fae5615f6bef269c821c89bac6a9d7e92afacb0aebe0",
            ignore/*macro_use*/
      );
 
@@ -1572,7 +2513,7 @@ mod app_test {
            "q\r",
            "macro_file_range",
            ">Run lsk\n>List range 20-25\n>Enter rust dir\nEnter redox dir\n>Open filesystem.toml\n>Quite Vim\n>Quite lsk",
-           "f5f1e7f641b5f348080ca2f86c0dffa8530cfab308cd9ec61d4cb9b8fa4cf3b7",
+           "b2c161fca3cccc84e909cf7f0e646d1f502e80253c28827795ecb7194a84636f",
            ignore/*macro_use*/
      );
 
@@ -1591,7 +2532,7 @@ mod app_test {
            "q\r",
            "macro_list_all_all_file_range",
            ">Run lsk\n>List all\n>Fuzzy search 'm'\n>List range 1-10\n>Enter mk dir\n>Open qemu.mk\n>Quite Vim\n>Go back/up a dir level\n>Quite lsk",
-           "0eea81e276ad3766422bf859f4d9d3e76b84ef11e9d5233fe1aa206f8115e4f8",
+           "27f32e7916ddb18bd2c5d9d5291247c14f7b429e229f8ec995f05cb00aebf60e",
            ignore/*macro_use*/
      );
 
@@ -1610,7 +2551,7 @@ mod app_test {
            "q\r",
            "macro_list_all_fuzzy_undo_open_range",
            ">Run lsk\n>List all\n>Fuzzy search 'i'\n>List range 5 - 17.\n>List range 7 open-ended\n>Open last one, key 17\n>Quite Vim\n>List entire range, 1-\n>Quite lsk",
-           "83796531204cd54871410774f6858b0e79b2e291eb7c0a77b06a1314bca8ebd7",
+           "d30178e8bea8f4f82d669fca57b1267b4ed998003b9fbcc5df6f03fe164d140b",
            ignore/*macro_use*/
      );
 
@@ -1629,7 +2570,7 @@ mod app_test {
            "q\r",
            "macro_list_all_fuzzy_dir",
            ">Run lsk\n>List all\n>Fuzzy search 'i'\n>List range 5 - 17.\n>List range 7 open-ended\n>Open rust-toolchain fie  with command vim\n>Quite Vim\n>List entire range, 1-\n>Quite lsk",
-           "20fc38f75c6739dbce70e86974cc2b7f58f8212279c47f05fe29f639ab1977f7",
+           "b71f1ee56e308ebac3222e3f641879a4dfd7b7613571fba918df32b88a57be75",
            ignore/*macro_use*/
      );
 
@@ -1650,11 +2591,115 @@ mod app_test {
            "q\r",
            "macro_list_all_fuzzy_dir",
            ">Run lsk\n>List all\n>Fuzzy search 'i'\n>List range 5 - 17.\n>List range 7 open-ended\n>Open bind dir fzd command, but main.rs doesn't show.\n>Quite lsk",
-           "9d2896a092c094c6073fe65408d38aa5d672dccf3f34597174774d8098194075",
+           "cdf57a3eb8f0037ebcbaa4409a1bf043772c1d0d7da43bdda0f6eefda1d26915",
            ignore/*macro_use*/
      );
 
 
+    #[test]
+    fn relist_keeps_the_filter() {
+        let path = "/tmp/lsk_tests/relist/";
+        let mut fixture = Fixture::new()
+            .add_dirpath(path.to_string())
+            .add_file(path.to_string() + "b")
+            .add_file(path.to_string() + "c")
+            .add_file(path.to_string() + "d")
+            .build();
+
+        let mut ls_key = LsKey::new(path, false, true, None, None, None);
+        ls_key.list.filter = Some(vec![2, 3]);
+        std::fs::File::create(path.to_string() + "a").unwrap();
+        ls_key.relist();
+
+        assert_eq!(ls_key.notice, Some("listing changed".to_string()));
+        assert_eq!(ls_key.list.filter, Some(vec![3, 4]));
+        let filtered: Vec<PathBuf> = ls_key.list.order_and_sort_list(true, true)
+            .into_iter()
+            .filter(|entry| entry.key.is_some())
+            .map(|entry| entry.path)
+            .collect();
+        assert_eq!(filtered, vec![PathBuf::from("c"), PathBuf::from("d")]);
+
+        fixture.teardown(true);
+    }
+
+    #[test]
+    fn summary_of_a_listing() {
+        let path = "/tmp/lsk_tests/summary/";
+        let mut fixture = Fixture::new()
+            .add_dirpath(path.to_string())
+            .add_dirpath(path.to_string() + "a-dir")
+            .add_file(path.to_string() + "a-file")
+            .add_file(path.to_string() + "b-file")
+            .add_file(path.to_string() + ".a-hidden-file")
+            .build();
+
+        let mut ls_key = LsKey::new(path, false, true, None, None, None);
+        ls_key.home = Some(PathBuf::from("/tmp/lsk_tests"));
+        ls_key.list.filter = Some(vec![2, 3]);
+        let summary = ls_key.summary();
+        assert_eq!(summary.path, "~/summary");
+        assert_eq!((summary.dirs, summary.files, summary.hidden), (1, 2, 1));
+        assert_eq!(summary.sort, "by name");
+        assert_eq!(summary.filter, Some("2-3".to_string()));
+
+        ls_key.list.filter = Some(vec![1, 3]);
+        assert_eq!(ls_key.summary().filter, Some("2 keys".to_string()));
+        ls_key.fuzzy_query = Some("fil".to_string());
+        assert_eq!(ls_key.summary().filter, Some("s fil".to_string()));
+
+        // The dir isn't read again until the listing changes.
+        std::fs::File::create(path.to_string() + ".b-hidden-file").unwrap();
+        assert_eq!(ls_key.summary().hidden, 1);
+        ls_key.relist();
+        assert_eq!(ls_key.summary().hidden, 2);
+
+        fixture.teardown(true);
+    }
+
+    #[test]
+    fn shell_output_files() {
+        let mut choosedir = std::env::temp_dir();
+        choosedir.push("lsk_test_choosedir");
+        let mut output = std::env::temp_dir();
+        output.push("lsk_test_output");
+        let _ = std::fs::remove_file(&output);
+
+        let shell_output = ShellOutput {
+            choosedir: Some(choosedir.clone()),
+            output: Some(output.clone()),
+            pick: false,
+        };
+
+        shell_output.choose_dir("/tmp/a dir").unwrap();
+        shell_output.return_paths(&[PathBuf::from("/tmp/a file"), PathBuf::from("/tmp/b")]).unwrap();
+        shell_output.return_paths(&[PathBuf::from("/tmp/c")]).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&choosedir).unwrap(), "/tmp/a dir");
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "/tmp/a file\n/tmp/b\n/tmp/c\n");
+        assert!(ShellOutput::default().return_paths(&[PathBuf::from("/tmp/c")]).is_err());
+
+        std::fs::remove_file(choosedir).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn shell_lines_on_the_terminal() {
+        assert_eq!(shell_on_terminal("!vim notes"), Some("vim notes"));
+        assert_eq!(shell_on_terminal("! less x"), Some("less x"));
+        assert_eq!(shell_on_terminal("!"), None);
+        assert_eq!(shell_on_terminal("! "), None);
+        assert_eq!(shell_on_terminal("git status"), None);
+    }
+
+    #[test]
+    fn separate_picked_paths() {
+        let paths = vec![PathBuf::from("/tmp/a file"), PathBuf::from("/tmp/b")];
+
+        assert_eq!(separate_paths(&paths, b'\n'), b"/tmp/a file\n/tmp/b\n".to_vec());
+        assert_eq!(separate_paths(&paths, b'\0'), b"/tmp/a file\0/tmp/b\0".to_vec());
+    }
+
     #[test]
     #[ignore]//docker
     fn parse() {
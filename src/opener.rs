@@ -0,0 +1,178 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+
+/// One `[[open]]` entry in the config, e.g.
+///
+/// ```toml
+/// [[open]]
+/// glob = "*.pdf"
+/// cmd = "zathura"
+///
+/// [[open]]
+/// mime = "image/*"
+/// cmd = "feh"
+///
+/// [[open]]
+/// ext = ["md", "txt"]
+/// cmd = "$EDITOR"
+/// ```
+///
+/// Every matcher given must match. The file is passed as the last argument
+/// to cmd, which runs under sh, so `$EDITOR` and friends work.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rule {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glob: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ext: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime: Option<String>,
+    pub cmd: String,
+}
+
+impl Rule {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.cmd.trim().is_empty() {
+            return Err("open rule without a cmd".to_string());
+        }
+        if self.glob.is_none() && self.ext.is_empty() && self.mime.is_none() {
+            return Err(format!("open rule for '{}' needs a glob, ext or mime", self.cmd));
+        }
+        if let Some(glob) = &self.glob {
+            Pattern::new(glob).map_err(|e| format!("bad glob '{}': {}", glob, e))?;
+        }
+
+        Ok(())
+    }
+
+    // Globs without a slash match the file name, like `*.pdf`.
+    pub fn matches(&self, path: &Path, mime: Option<&str>) -> bool {
+        if let Some(glob) = &self.glob {
+            let target = if glob.contains('/') {
+                path.to_string_lossy()
+            } else {
+                match path.file_name() {
+                    Some(name) => name.to_string_lossy(),
+                    None => return false
+                }
+            };
+            match Pattern::new(glob) {
+                Ok(pattern) if pattern.matches(&target) => (),
+                _ => return false
+            }
+        }
+
+        if !self.ext.is_empty() {
+            let ext = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+            match ext {
+                Some(ext) if self.ext.iter().any(|e| e.trim_start_matches('.').to_lowercase() == ext) => (),
+                _ => return false
+            }
+        }
+
+        if let Some(want) = &self.mime {
+            match mime {
+                Some(mime) if mime_matches(want, mime) => (),
+                _ => return false
+            }
+        }
+
+        true
+    }
+}
+
+// `image/*` matches any image; anything else must be exact.
+fn mime_matches(want: &str, mime: &str) -> bool {
+    match want.strip_suffix("/*") {
+        Some(top) => mime.split('/').next() == Some(top),
+        None => want == mime
+    }
+}
+
+/// The rules that match path, in config order. The MIME type is only
+/// sniffed if some rule asks for it.
+pub fn handlers<'a>(rules: &'a [Rule], path: &Path) -> Vec<&'a Rule> {
+    let mime = if rules.iter().any(|rule| rule.mime.is_some()) {
+        Some(mime_type(path))
+    } else {
+        None
+    };
+
+    rules
+        .iter()
+        .filter(|rule| rule.matches(path, mime.as_deref()))
+        .collect()
+}
+
+/// Magic bytes first, then the extension, then a look at the contents.
+pub fn mime_type(path: &Path) -> String {
+    if let Ok(Some(kind)) = infer::get_from_path(path) {
+        return kind.mime_type().to_string();
+    }
+    if let Some(mime) = mime_guess::from_path(path).first_raw() {
+        return mime.to_string();
+    }
+
+    let mut head = vec![0; 1024];
+    let read = File::open(path).and_then(|mut file| file.read(&mut head));
+    match read {
+        Ok(n) if looks_like_text(&head[..n]) => "text/plain".to_string(),
+        _ => "application/octet-stream".to_string()
+    }
+}
+
+// A cut-off multibyte char at the end is fine.
+fn looks_like_text(bytes: &[u8]) -> bool {
+    !bytes.contains(&0) && match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn rule(glob: Option<&str>, ext: &[&str], mime: Option<&str>) -> Rule {
+        Rule {
+            glob: glob.map(String::from),
+            ext: ext.iter().map(|e| e.to_string()).collect(),
+            mime: mime.map(String::from),
+            cmd: "true".to_string(),
+        }
+    }
+
+    #[test]
+    fn match_rules() {
+        let pdf = PathBuf::from("/home/me/docs/paper.PDF");
+
+        assert!(rule(Some("*.PDF"), &[], None).matches(&pdf, None));
+        assert!(!rule(Some("*.pdf"), &[], None).matches(&pdf, None));
+        assert!(rule(Some("/home/*/docs/*"), &[], None).matches(&pdf, None));
+        assert!(rule(None, &["pdf", "djvu"], None).matches(&pdf, None));
+        assert!(rule(None, &[".pdf"], None).matches(&pdf, None));
+        assert!(rule(None, &[], Some("application/*")).matches(&pdf, Some("application/pdf")));
+        assert!(!rule(None, &[], Some("image/*")).matches(&pdf, Some("application/pdf")));
+        assert!(!rule(Some("*.PDF"), &[], Some("text/plain")).matches(&pdf, Some("application/pdf")));
+    }
+
+    #[test]
+    fn validate_rules() {
+        assert!(rule(Some("*.pdf"), &[], None).validate().is_ok());
+        assert!(rule(None, &[], None).validate().is_err());
+        assert!(rule(Some("[*.pdf"), &[], None).validate().is_err());
+        assert!(Rule { cmd: "".to_string(), ..rule(Some("*"), &[], None) }.validate().is_err());
+    }
+
+    #[test]
+    fn text_sniffing() {
+        assert!(looks_like_text(b"fn main() {}\n"));
+        assert!(looks_like_text(&"caf\u{e9}".as_bytes()[..4]));
+        assert!(!looks_like_text(b"\x7fELF\0\0"));
+    }
+}
@@ -0,0 +1,239 @@
+use std::ops::Range;
+use term_grid::{Cell, Direction, Filling, Grid, GridOptions};
+use super::terminal::input_n_display::{plain, visible_width};
+
+/// The space a page is laid out in.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Shape {
+    pub width: usize,
+    pub rows: usize,
+    // One entry per line, as in the tree view and tests.
+    pub one_column: bool,
+}
+
+/// Where a key is drawn: its line of the page, and the columns it spans
+/// there, counting on past the screen's edge when the line wraps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spot {
+    pub line: usize,
+    pub columns: Range<usize>,
+    pub key: usize,
+}
+
+/// Keyed labels split into screenfuls. A page holds as many as its grid
+/// fits in the rows, counting the extra rows a name takes when it's wider
+/// than the screen and wraps. The header, e.g. `../dir`, heads every page.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Pages {
+    header: Option<String>,
+    labels: Vec<String>,
+    keys: Vec<usize>,
+    shape: Shape,
+    // Where each page starts in labels.
+    starts: Vec<usize>,
+}
+
+impl Pages {
+    pub fn new(header: Option<String>, labels: Vec<String>, keys: Vec<usize>, shape: Shape) -> Self {
+        let mut pages = Pages { header, labels, keys, shape, starts: vec![0] };
+        let mut start = 0;
+        // Pages tend to hold about as many as the one before.
+        let mut guess = shape.rows.max(1);
+        while start < pages.labels.len() {
+            let n = pages.most_from(start, guess);
+            guess = n;
+            start += n;
+            if start < pages.labels.len() {
+                pages.starts.push(start);
+            }
+        }
+
+        pages
+    }
+
+    /// Whether these are the labels laid out, whatever the shape.
+    pub fn shows(&self, header: &Option<String>, labels: &[String]) -> bool {
+        self.header == *header && self.labels == labels
+    }
+
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    pub fn count(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Label indexes on the page; past the last page is the last page.
+    pub fn range(&self, page: usize) -> Range<usize> {
+        let page = page.min(self.count() - 1);
+        let end = self.starts.get(page + 1).copied().unwrap_or(self.labels.len());
+
+        self.starts[page]..end
+    }
+
+    /// The page label index is on.
+    pub fn page_of(&self, index: usize) -> usize {
+        self.starts.partition_point(|start| *start <= index).saturating_sub(1)
+    }
+
+    /// The page key is on, or would be on. Keys only ever go up.
+    pub fn page_of_key(&self, key: usize) -> usize {
+        match self.keys.binary_search(&key) {
+            Ok(index) | Err(index) => self.page_of(index)
+        }
+    }
+
+    pub fn render(&self, page: usize) -> String {
+        render(self.header.as_ref(), &self.labels[self.range(page)], self.shape)
+    }
+
+    /// Where each key in render(page) ended up. The header is key 0.
+    pub fn spots(&self, page: usize, rendered: &str) -> Vec<Spot> {
+        let range = self.range(page);
+        let header = self.header.iter().map(|header| (header, 0));
+        let mut cells = header.chain(self.labels[range.clone()].iter().zip(self.keys[range].iter().copied())).peekable();
+        let mut spots = vec![];
+        for (line, text) in rendered.lines().enumerate() {
+            let text: Vec<char> = plain(text).chars().collect();
+            let mut from = 0;
+            while let Some((label, key)) = cells.peek() {
+                let label: Vec<char> = plain(label).chars().collect();
+                let found = (from..(text.len() + 1).saturating_sub(label.len())).find(|at| text[*at..*at + label.len()] == label[..]);
+                match found {
+                    Some(at) => {
+                        spots.push(Spot { line, columns: at..at + label.len(), key: *key });
+                        from = at + label.len();
+                        cells.next();
+                    },
+                    None => break
+                }
+            }
+        }
+
+        spots
+    }
+
+    // How many from start fit on a page; at least one, even if it doesn't.
+    fn most_from(&self, start: usize, guess: usize) -> usize {
+        let left = self.labels.len() - start;
+        let fits = |n: usize| n <= 1 || rows(&render(self.header.as_ref(), &self.labels[start..start + n], self.shape), self.shape.width) <= self.shape.rows;
+        let guess = guess.clamp(1, left);
+        let guess_fits = fits(guess);
+        if guess_fits && (guess == left || !fits(guess + 1)) {
+            return guess;
+        }
+
+        // Gallop to a bound, then halve down to the most that fits.
+        let (mut low, mut high) = (1, guess);
+        if guess_fits {
+            low = guess;
+            loop {
+                if low == left {
+                    return low;
+                }
+                high = (low * 2).min(left);
+                if !fits(high) {
+                    break;
+                }
+                low = high;
+            }
+        }
+        while high - low > 1 {
+            let middle = (low + high) / 2;
+            if fits(middle) {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+
+        low
+    }
+}
+
+fn render(header: Option<&String>, labels: &[String], shape: Shape) -> String {
+    let mut grid = Grid::new(GridOptions {
+        filling: Filling::Spaces(3),
+        direction: Direction::LeftToRight,
+    });
+    for label in header.into_iter().chain(labels) {
+        grid.add(Cell { contents: label.clone(), width: visible_width(label) });
+    }
+
+    match grid.fit_into_width(shape.width) {
+        Some(display) if !shape.one_column => display.to_string(),
+        _ => grid.fit_into_columns(1).to_string()
+    }
+}
+
+// Screen rows the text takes, long lines wrapping.
+fn rows(text: &str, width: usize) -> usize {
+    text.lines()
+        .map(|line| visible_width(line).max(1).div_ceil(width.max(1)))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(names: &[&str]) -> (Vec<String>, Vec<usize>) {
+        let labels = names.iter().enumerate().map(|(n, name)| format!("{} [{}]", name, n + 1)).collect();
+
+        (labels, (1..=names.len()).collect())
+    }
+
+    #[test]
+    fn wrapped_names_take_their_rows() {
+        let shape = Shape { width: 10, rows: 4, one_column: true };
+        let (labels, keys) = labels(&["a", "bb", "a-name-that-wraps-twice", "c", "d"]);
+        let pages = Pages::new(Some("../up".to_string()), labels, keys, shape);
+
+        // ../up, a and bb; then ../up and the three-row name; then the rest.
+        assert_eq!(pages.count(), 3);
+        assert_eq!(pages.range(0), 0..2);
+        assert_eq!(pages.range(1), 2..3);
+        assert_eq!(pages.range(2), 3..5);
+        assert_eq!(pages.page_of_key(3), 1);
+        assert_eq!(pages.page_of_key(99), 2);
+        assert!(pages.render(1).starts_with("../up\n"));
+    }
+
+    #[test]
+    fn spots_follow_the_grid() {
+        let shape = Shape { width: 30, rows: 5, one_column: false };
+        let (labels, keys) = labels(&["aa", "bbbbbb", "c", "dd", "eeee"]);
+        let labels = labels.into_iter().map(|label| format!("\x1b[1m{}\x1b[0m", label)).collect();
+        let pages = Pages::new(Some("../up".to_string()), labels, keys, shape);
+        let rendered = pages.render(0);
+        let spots = pages.spots(0, &rendered);
+
+        assert_eq!(spots.len(), 6);
+        assert_eq!(spots[0], Spot { line: 0, columns: 0..5, key: 0 });
+        for spot in &spots {
+            let line: Vec<char> = plain(rendered.lines().nth(spot.line).unwrap()).chars().collect();
+            let text: String = line[spot.columns.clone()].iter().collect();
+            assert!(text.ends_with(&format!("[{}]", spot.key)) || spot.key == 0);
+        }
+    }
+
+    #[test]
+    fn grid_pages_cover_everything() {
+        let shape = Shape { width: 40, rows: 3, one_column: false };
+        let names: Vec<String> = (0..100).map(|n| format!("file{}", n)).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let (labels, keys) = labels(&names);
+        let pages = Pages::new(None, labels, keys, shape);
+
+        let mut next = 0;
+        for page in 0..pages.count() {
+            let range = pages.range(page);
+            assert_eq!(range.start, next);
+            assert!(rows(&pages.render(page), 40) <= 3);
+            next = range.end;
+        }
+        assert_eq!(next, 100);
+        assert!(pages.count() > 1);
+    }
+}
@@ -0,0 +1,208 @@
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+/// The bar along the bottom unless the config's `status` says otherwise.
+pub const DEFAULT: &str = "{path}[  {branch}]  {dirs} dirs, {files} files[, {hidden} hidden]  {size}  {sort}[  {filter}][  {marks} marked]";
+
+const FIELDS: &[&str] = &["path", "files", "dirs", "hidden", "size", "branch", "sort", "filter", "marks"];
+
+/// What the template's `{fields}` are filled in with.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Summary {
+    pub path: String,
+    pub files: usize,
+    pub dirs: usize,
+    pub hidden: usize,
+    pub size: String,
+    pub branch: Option<String>,
+    pub sort: String,
+    pub filter: Option<String>,
+    pub marks: usize,
+}
+
+impl Summary {
+    fn field(&self, name: &str) -> String {
+        match name {
+            "path" => self.path.clone(),
+            "files" => self.files.to_string(),
+            "dirs" => self.dirs.to_string(),
+            "hidden" => self.hidden.to_string(),
+            "size" => self.size.clone(),
+            "branch" => self.branch.clone().unwrap_or_default(),
+            "sort" => self.sort.clone(),
+            "filter" => self.filter.clone().unwrap_or_default(),
+            "marks" => self.marks.to_string(),
+            _ => String::new()
+        }
+    }
+}
+
+// A piece of the template: text, a field, or a [group] of them.
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Field(String),
+    Group(Vec<Part>),
+}
+
+/// A status line template, e.g. "{path}  {files} files[  {branch}]".
+/// A [group] is left out when a field in it is empty or 0.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = vec![];
+        let mut group: Option<Vec<Part>> = None;
+        let mut rest = template;
+        while let Some(c) = rest.chars().next() {
+            let part = match c {
+                '{' => {
+                    let (name, after) = rest[1..].split_once('}').ok_or("status: unclosed {")?;
+                    if !FIELDS.contains(&name) {
+                        return Err(format!("status: unknown field {{{}}}, expected one of {}", name, FIELDS.join(", ")));
+                    }
+                    rest = after;
+                    Part::Field(name.to_string())
+                },
+                '[' if group.is_none() => {
+                    group = Some(vec![]);
+                    rest = &rest[1..];
+                    continue;
+                },
+                '[' => return Err("status: [groups] don't nest".to_string()),
+                ']' => {
+                    rest = &rest[1..];
+                    Part::Group(group.take().ok_or("status: ] without a [")?)
+                },
+                _ => {
+                    let end = rest.find(|c| "{[]".contains(c)).unwrap_or(rest.len());
+                    let text = rest[..end].to_string();
+                    rest = &rest[end..];
+                    Part::Text(text)
+                }
+            };
+            match (&mut group, part) {
+                (Some(group), part @ Part::Text(_)) | (Some(group), part @ Part::Field(_)) => group.push(part),
+                (_, part) => parts.push(part)
+            }
+        }
+        if group.is_some() {
+            return Err("status: unclosed [".to_string());
+        }
+
+        Ok(Template { parts })
+    }
+
+    /// Nothing to show for an empty template.
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    pub fn render(&self, summary: &Summary) -> String {
+        render(&self.parts, summary)
+    }
+}
+
+fn render(parts: &[Part], summary: &Summary) -> String {
+    let mut line = String::new();
+    for part in parts {
+        match part {
+            Part::Text(text) => line.push_str(text),
+            Part::Field(name) => line.push_str(&summary.field(name)),
+            Part::Group(parts) => {
+                // Left out if a field came out empty or 0.
+                let empty = parts.iter().any(|part| match part {
+                    Part::Field(name) => matches!(summary.field(name).as_str(), "" | "0"),
+                    _ => false
+                });
+                if !empty {
+                    line.push_str(&render(parts, summary));
+                }
+            }
+        }
+    }
+
+    line
+}
+
+/// dir with home shown as ~.
+pub fn tilde(dir: &Path, home: Option<&Path>) -> String {
+    match home.and_then(|home| dir.strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => dir.display().to_string()
+    }
+}
+
+/// The branch checked out in the repo dir is in, or the commit if detached.
+pub fn git_branch(dir: &Path) -> Option<String> {
+    let git = dir.ancestors().map(|dir| dir.join(".git")).find(|git| git.exists())?;
+    // A worktree or submodule has a file pointing at its git dir instead.
+    let git = match read_to_string(&git).ok().and_then(|file| file.strip_prefix("gitdir:").map(|to| to.trim().to_string())) {
+        Some(to) => git.parent().map_or(PathBuf::from(&to), |parent| parent.join(&to)),
+        None => git
+    };
+    let head = read_to_string(git.join("HEAD")).ok()?;
+
+    branch(head.trim())
+}
+
+fn branch(head: &str) -> Option<String> {
+    match head.strip_prefix("ref: ") {
+        Some(reference) => Some(reference.strip_prefix("refs/heads/").unwrap_or(reference).to_string()),
+        None if head.len() >= 7 => Some(head[..7].to_string()),
+        None => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary() -> Summary {
+        Summary {
+            path: "~/src".to_string(),
+            files: 3,
+            dirs: 2,
+            size: "1.5K".to_string(),
+            sort: "by name".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn groups_drop_out_when_empty() {
+        let template = Template::parse(DEFAULT).unwrap();
+        assert_eq!(template.render(&summary()), "~/src  2 dirs, 3 files  1.5K  by name");
+
+        let busy = Summary { hidden: 4, branch: Some("main".to_string()), filter: Some("5-7".to_string()), marks: 1, ..summary() };
+        assert_eq!(template.render(&busy), "~/src  main  2 dirs, 3 files, 4 hidden  1.5K  by name  5-7  1 marked");
+
+        assert!(Template::parse("").unwrap().is_empty());
+        assert_eq!(Template::parse("{files}/{hidden}").unwrap().render(&summary()), "3/0");
+    }
+
+    #[test]
+    fn parse_bad_template() {
+        assert!(Template::parse("{colour}").is_err());
+        assert!(Template::parse("{path").is_err());
+        assert!(Template::parse("[{path}").is_err());
+        assert!(Template::parse("{path}]").is_err());
+        assert!(Template::parse("[[{path}]]").is_err());
+    }
+
+    #[test]
+    fn home_and_head() {
+        let home = Path::new("/home/me");
+        assert_eq!(tilde(Path::new("/home/me"), Some(home)), "~");
+        assert_eq!(tilde(Path::new("/home/me/src"), Some(home)), "~/src");
+        assert_eq!(tilde(Path::new("/home/meg"), Some(home)), "/home/meg");
+        assert_eq!(tilde(Path::new("/tmp"), None), "/tmp");
+
+        assert_eq!(branch("ref: refs/heads/main"), Some("main".to_string()));
+        assert_eq!(branch("3f2a9c1d0b8e7f6a5c4d3e2f1a0b9c8d7e6f5a4b"), Some("3f2a9c1".to_string()));
+    }
+}
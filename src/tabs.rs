@@ -0,0 +1,128 @@
+use std::path::{Path, PathBuf};
+use ansi_term::Style;
+use super::history::History;
+use super::list::List;
+
+/// What a tab keeps while another one is in front.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Session {
+    pub list: List,
+    pub history: History,
+    pub fuzzy_query: Option<String>,
+}
+
+/// The open tabs. The one in front lives in `LsKey` itself, so its slot
+/// here is only brought up to date when switching away from it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Tabs {
+    sessions: Vec<Session>,
+    active: usize,
+}
+
+impl Tabs {
+    pub fn count(&self) -> usize {
+        self.sessions.len().max(1)
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Put new in front of current, as the last tab.
+    pub fn open(&mut self, current: Session, new: Session) {
+        self.stash(current);
+        self.sessions.push(new);
+        self.active = self.sessions.len() - 1;
+    }
+
+    /// Tabs count from 0 here and from 1 on screen.
+    pub fn switch(&mut self, current: Session, to: usize) -> Result<Session, String> {
+        if to >= self.count() {
+            return Err(format!("no tab {}", to + 1));
+        }
+        self.stash(current.clone());
+        self.active = to;
+
+        Ok(self.sessions.get(to).cloned().unwrap_or(current))
+    }
+
+    /// Close the tab in front and bring up its neighbour.
+    pub fn close(&mut self) -> Result<Session, String> {
+        if self.count() < 2 {
+            return Err("can't close the last tab".to_string());
+        }
+        self.sessions.remove(self.active);
+        if self.active == self.sessions.len() {
+            self.active -= 1;
+        }
+
+        Ok(self.sessions[self.active].clone())
+    }
+
+    /// The dir tab n is in. The tab in front may have moved on, so it's asked for.
+    pub fn dir(&self, n: usize, current_dir: &Path) -> Option<PathBuf> {
+        if n == self.active {
+            Some(current_dir.to_path_buf())
+        } else {
+            self.sessions.get(n).map(|session| session.list.parent_path.clone())
+        }
+    }
+
+    /// Numbered dir names, the one in front highlighted. None with one tab.
+    pub fn bar(&self, current_dir: &Path) -> Option<String> {
+        if self.count() < 2 {
+            return None;
+        }
+
+        let titles: Vec<String> = (0..self.count()).map(|n| {
+            let dir = self.dir(n, current_dir).unwrap_or_default();
+            let name = dir.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_else(|| dir.display().to_string());
+            let title = format!(" {} {} ", n + 1, name);
+            if n == self.active {
+                Style::new().reverse().paint(title).to_string()
+            } else {
+                title
+            }
+        }).collect();
+
+        Some(titles.join(""))
+    }
+
+    fn stash(&mut self, current: Session) {
+        if self.sessions.is_empty() {
+            self.sessions.push(current);
+        } else {
+            self.sessions[self.active] = current;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(dir: &str) -> Session {
+        Session { list: List::new(dir), ..Default::default() }
+    }
+
+    #[test]
+    fn open_switch_close() {
+        let mut tabs = Tabs::default();
+        assert_eq!(tabs.count(), 1);
+        assert_eq!(tabs.bar(Path::new("/a")), None);
+        assert!(tabs.close().is_err());
+
+        tabs.open(at("/a"), at("/b"));
+        tabs.open(at("/b2"), at("/c"));
+        assert_eq!(tabs.count(), 3);
+        assert_eq!(tabs.active(), 2);
+        assert_eq!(tabs.dir(1, Path::new("/c")), Some(PathBuf::from("/b2")));
+
+        assert_eq!(tabs.switch(at("/c"), 0), Ok(at("/a")));
+        assert!(tabs.switch(at("/a"), 3).is_err());
+        assert_eq!(tabs.close(), Ok(at("/b2")));
+        assert_eq!(tabs.active(), 0);
+        assert_eq!(tabs.dir(1, Path::new("/b2")), Some(PathBuf::from("/c")));
+        assert!(tabs.bar(Path::new("/b2")).unwrap().contains(" 2 c "));
+    }
+}
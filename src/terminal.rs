@@ -76,7 +76,7 @@ pub mod input_n_display {
 
     // (columns/width, lines/height)
     pub fn size() -> (u16, u16) {
-        terminal_size().unwrap()
+        terminal_size().unwrap_or((80, 24))
     }
 
     pub fn alternate_screen() {
@@ -302,6 +302,59 @@ pub mod input_n_display {
         }
     }
 
+    // Columns wide, not counting colour codes.
+    pub fn visible_width(s: &str) -> usize {
+        let mut width = 0;
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            } else {
+                width += 1;
+            }
+        }
+
+        width
+    }
+
+    // Without the colour codes.
+    pub fn plain(s: &str) -> String {
+        let mut plain = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            } else {
+                plain.push(c);
+            }
+        }
+
+        plain
+    }
+
+    // Two blocks of lines as columns, the right one starting at column.
+    pub fn side_by_side(left: &str, right: &str, column: usize) -> String {
+        let left: Vec<&str> = left.lines().collect();
+        let right: Vec<&str> = right.lines().collect();
+        let mut lines: Vec<String> = vec![];
+        for n in 0..left.len().max(right.len()) {
+            let l = left.get(n).copied().unwrap_or("");
+            let r = right.get(n).copied().unwrap_or("");
+            let pad = column.saturating_sub(visible_width(l)).max(1);
+            lines.push(format!("{}{}{}", l, " ".repeat(pad), r).trim_end().to_string());
+        }
+
+        lines.join("\n") + "\n"
+    }
+
     pub fn grid_display(entries: Vec<String>) /*Result<(Grid), Error>*/{
         let mut grid = Grid::new(GridOptions {
                 filling:     Filling::Spaces(3),
@@ -313,7 +366,7 @@ pub mod input_n_display {
                 grid.add(Cell::from(s.as_str()));
         }
 
-        let (w, _) = terminal_size()/*; match this     */.unwrap();
+        let (w, _) = terminal_size().unwrap_or((80, 24));
         /*match (w, h) {
             Ok((w, h)) => {
                 let w = usize::from(w);
@@ -332,16 +385,129 @@ pub mod input_n_display {
     }
 }
 
+pub mod tty {
+    use std::fs::{File, OpenOptions};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+
+    // Point stdout (and stdin, unless it's already a terminal) at /dev/tty,
+    // so the TUI can be drawn while the real stdout is captured, e.g.
+    // `vim $(lsk --pick)`. Returns the original stdout.
+    pub fn take_stdout() -> Result<File, std::io::Error> {
+        let tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")?;
+
+        unsafe {
+            let original = libc::dup(libc::STDOUT_FILENO);
+            if original < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            if libc::dup2(tty.as_raw_fd(), libc::STDOUT_FILENO) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            if libc::isatty(libc::STDIN_FILENO) == 0
+                && libc::dup2(tty.as_raw_fd(), libc::STDIN_FILENO) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            Ok(File::from_raw_fd(original))
+        }
+    }
+
+    /// Stdin without std's buffer, so `ready` can tell when a key is
+    /// waiting. Escape sequences still come through whole.
+    pub struct Stdin;
+
+    impl std::io::Read for Stdin {
+        // termion asks for two bytes at a time to tell Esc from the start of
+        // an escape sequence, and keeps the second if it's unused, out of
+        // ready's sight. So only an Esc gets company.
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if buf.is_empty() {
+                return Ok(0);
+            }
+            let n = read_stdin(&mut buf[..1])?;
+            if n == 1 && buf[0] == b'\x1B' && buf.len() > 1 && ready(std::time::Duration::from_millis(0)) {
+                Ok(1 + read_stdin(&mut buf[1..2])?)
+            } else {
+                Ok(n)
+            }
+        }
+    }
+
+    fn read_stdin(buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = unsafe { libc::read(libc::STDIN_FILENO, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if n < 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(n as usize)
+        }
+    }
+
+    static RESIZED: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn on_resize(_: libc::c_int) {
+        RESIZED.store(true, Ordering::SeqCst);
+    }
+
+    // SIGWINCH cuts ready's wait short, so a resize is redrawn at once.
+    pub fn catch_resize() {
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_resize as *const () as usize;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(libc::SIGWINCH, &action, std::ptr::null_mut());
+        }
+    }
+
+    /// Turn mouse reports on and off: clicks and the wheel, in SGR form so
+    /// wide terminals work. termion's MouseTerminal would change the
+    /// screen's type wherever it's passed.
+    pub const MOUSE_ON: &str = "\x1b[?1000h\x1b[?1006h";
+    pub const MOUSE_OFF: &str = "\x1b[?1006l\x1b[?1000l";
+
+    /// True once per resize since catch_resize.
+    pub fn resized() -> bool {
+        RESIZED.swap(false, Ordering::SeqCst)
+    }
+
+    // Wait up to timeout for input, so the screen can be redrawn meanwhile.
+    pub fn ready(timeout: std::time::Duration) -> bool {
+        let mut fd = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+        let n = unsafe { libc::poll(&mut fd, 1, timeout.as_millis() as libc::c_int) };
+
+        n > 0
+    }
+}
+
 pub mod shell {
     use cmd_lib::run_fun;
+    use std::env;
+    use std::ffi::OsStr;
+    use std::path::Path;
 
-    pub fn spawn(cmd: String, args: Vec<String>) {
+    pub fn spawn<S: AsRef<OsStr>>(cmd: String, args: Vec<S>) -> Result<(), std::io::Error> {
         std::process::Command::new(cmd)
             .args(args)
-            .spawn()
-            .expect("failed to execute shell process.")
+            .spawn()?
+            .wait()
+            .map(drop)
+    }
+
+    // Run an opener rule's cmd under sh with file as its last argument.
+    pub fn open_with<P: AsRef<Path>>(cmd: &str, file: P, editor: &str) -> Result<(), std::io::Error> {
+        std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$1\"", cmd))
+            .arg("lsk")
+            .arg(file.as_ref())
+            .env("EDITOR", editor)
+            .spawn()?
             .wait()
-            .expect("unrecoverable failure to execute shell process.");
+            .map(drop)
     }
 
     pub fn output(cmd: String, args: Vec<String>) -> Result<std::process::Output, std::io::Error> {
@@ -353,6 +519,29 @@ pub mod shell {
     pub fn cmd(cmd: String) -> Result<String, std::io::Error> {
         run_fun!("{}", cmd)
     }
+
+    // Drop into the user's $SHELL (or /bin/sh) at dir. LSK_LEVEL is bumped
+    // so a prompt can show how deeply the shell is nested inside lsk.
+    pub fn subshell<P: AsRef<Path>>(dir: P) -> Result<(), std::io::Error> {
+        let shell = match env::var("SHELL") {
+            Ok(shell) if !shell.is_empty() => shell,
+            _ => "/bin/sh".to_string()
+        };
+        let level = lsk_level(env::var("LSK_LEVEL").ok());
+
+        std::process::Command::new(shell)
+            .current_dir(dir)
+            .env("LSK_LEVEL", level.to_string())
+            .spawn()?
+            .wait()
+            .map(drop)
+    }
+
+    pub fn lsk_level(current: Option<String>) -> usize {
+        current
+            .and_then(|level| level.parse::<usize>().ok())
+            .unwrap_or(0) + 1
+    }
 }
 
 pub mod grid_display {
@@ -464,6 +653,24 @@ mod tests {
         super::input_n_display::read().expect("failed to read input");
     }
 
+    #[test]
+    fn subshell_level() {
+        assert_eq!(super::shell::lsk_level(None), 1);
+        assert_eq!(super::shell::lsk_level(Some("2".to_string())), 3);
+        assert_eq!(super::shell::lsk_level(Some("bogus".to_string())), 1);
+    }
+
+    #[test]
+    fn panes_side_by_side() {
+        use super::input_n_display::{side_by_side, visible_width};
+
+        assert_eq!(visible_width("\x1b[1;34mdir [1]\x1b[0m"), 7);
+        assert_eq!(
+            side_by_side("\x1b[1;34ma [1]\x1b[0m\nbb [2]\n", "c [b1]\n", 10),
+            "\x1b[1;34ma [1]\x1b[0m     c [b1]\nbb [2]\n"
+        );
+    }
+
     #[test]
     #[ignore]//docker
     fn display_grid() {
@@ -0,0 +1,115 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Tree view: the dir's entries with subdirs expanded in place.
+///
+/// Dirs shallower than depth start out expanded, so depth 1 is the plain
+/// listing and `lsk --tree -L 3` shows three levels. Keying a dir flips it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tree {
+    pub depth: usize,
+    toggled: BTreeSet<PathBuf>,
+}
+
+impl Default for Tree {
+    fn default() -> Self {
+        Tree::new(1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub key: usize,
+    // Branch glyphs, e.g. "│   └── ".
+    pub prefix: String,
+}
+
+impl Tree {
+    pub fn new(depth: usize) -> Self {
+        Tree { depth: depth.max(1), toggled: BTreeSet::new() }
+    }
+
+    pub fn is_expanded(&self, dir: &Path, depth: usize) -> bool {
+        (depth < self.depth) != self.toggled.contains(dir)
+    }
+
+    pub fn toggle<P: AsRef<Path>>(&mut self, dir: P) {
+        let dir = dir.as_ref().to_path_buf();
+        if !self.toggled.remove(&dir) {
+            self.toggled.insert(dir);
+        }
+    }
+
+    /// The visible nodes, keyed depth-first from 1. top is the dir's own
+    /// entries, in listing order; children lists a subdir's the same way.
+    pub fn nodes<F>(&self, top: Vec<(PathBuf, bool)>, children: F) -> Vec<Node>
+        where F: Fn(&Path) -> Vec<(PathBuf, bool)>
+    {
+        let mut nodes = vec![];
+        self.walk(top, 1, "", &children, &mut nodes);
+
+        nodes
+    }
+
+    fn walk<F>(&self, entries: Vec<(PathBuf, bool)>, depth: usize, indent: &str, children: &F, nodes: &mut Vec<Node>)
+        where F: Fn(&Path) -> Vec<(PathBuf, bool)>
+    {
+        let count = entries.len();
+        for (n, (path, is_dir)) in entries.into_iter().enumerate() {
+            let last = n + 1 == count;
+            let branch = if last { "└── " } else { "├── " };
+            nodes.push(Node {
+                path: path.clone(),
+                is_dir,
+                key: nodes.len() + 1,
+                prefix: format!("{}{}", indent, branch),
+            });
+            if is_dir && self.is_expanded(&path, depth) {
+                let indent = format!("{}{}", indent, if last { "    " } else { "│   " });
+                self.walk(children(&path), depth + 1, &indent, children, nodes);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn children(dir: &Path) -> Vec<(PathBuf, bool)> {
+        match dir.to_str().unwrap() {
+            "/r/a" => vec![(PathBuf::from("/r/a/x"), true), (PathBuf::from("/r/a/y"), false)],
+            "/r/a/x" => vec![(PathBuf::from("/r/a/x/z"), false)],
+            _ => vec![]
+        }
+    }
+
+    fn top() -> Vec<(PathBuf, bool)> {
+        vec![(PathBuf::from("/r/a"), true), (PathBuf::from("/r/b"), false)]
+    }
+
+    fn lines(nodes: &[Node]) -> Vec<String> {
+        nodes.iter().map(|node| format!("{}{} [{}]", node.prefix, node.path.file_name().unwrap().to_str().unwrap(), node.key)).collect()
+    }
+
+    #[test]
+    fn depth_and_toggling() {
+        assert_eq!(lines(&Tree::new(1).nodes(top(), children)), vec!["├── a [1]", "└── b [2]"]);
+
+        assert_eq!(lines(&Tree::new(3).nodes(top(), children)), vec![
+            "├── a [1]",
+            "│   ├── x [2]",
+            "│   │   └── z [3]",
+            "│   └── y [4]",
+            "└── b [5]",
+        ]);
+
+        let mut tree = Tree::new(2);
+        tree.toggle("/r/a/x");
+        assert_eq!(tree.nodes(top(), children).len(), 5);
+        tree.toggle("/r/a");
+        assert_eq!(lines(&tree.nodes(top(), children)), vec!["├── a [1]", "└── b [2]"]);
+    }
+}
@@ -0,0 +1,115 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Tells when entries come and go in a dir. Uses inotify, so on other
+/// systems nothing ever changes.
+#[derive(Debug, Clone)]
+pub struct Watcher {
+    pub dir: PathBuf,
+    // Closed when the last clone goes.
+    inotify: Option<Arc<File>>,
+}
+
+impl PartialEq for Watcher {
+    fn eq(&self, other: &Self) -> bool {
+        self.dir == other.dir
+    }
+}
+
+impl Watcher {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        let dir = dir.as_ref().to_path_buf();
+        let inotify = inotify::watch(&dir).map(Arc::new);
+
+        Watcher { dir, inotify }
+    }
+
+    /// True if anything was added, removed or renamed since last asked.
+    pub fn changed(&self) -> bool {
+        match &self.inotify {
+            Some(inotify) => inotify::drain(inotify),
+            None => false
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::ffi::CString;
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::FromRawFd;
+    use std::path::Path;
+
+    const EVENTS: u32 = libc::IN_CREATE | libc::IN_DELETE | libc::IN_MOVED_FROM | libc::IN_MOVED_TO
+        | libc::IN_DELETE_SELF | libc::IN_MOVE_SELF;
+
+    // None if it can't be watched, e.g. out of inotify watches.
+    pub fn watch(dir: &Path) -> Option<File> {
+        let path = CString::new(dir.as_os_str().as_bytes()).ok()?;
+        unsafe {
+            let fd = libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC);
+            if fd < 0 {
+                return None;
+            }
+            let inotify = File::from_raw_fd(fd);
+            if libc::inotify_add_watch(fd, path.as_ptr(), EVENTS) < 0 {
+                return None;
+            }
+
+            Some(inotify)
+        }
+    }
+
+    // Non-blocking, so it stops at WouldBlock once the events are read.
+    pub fn drain(mut inotify: &File) -> bool {
+        let mut buf = [0u8; 4096];
+        let mut changed = false;
+        while let Ok(n) = inotify.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            changed = true;
+        }
+
+        changed
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod inotify {
+    use std::fs::File;
+    use std::path::Path;
+
+    pub fn watch(_dir: &Path) -> Option<File> {
+        None
+    }
+
+    pub fn drain(_inotify: &File) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    #[test]
+    #[ignore]//docker
+    fn sees_new_files() {
+        let dir = Path::new("/tmp/lsk_watch_test");
+        let _ = remove_dir_all(dir);
+        create_dir_all(dir).unwrap();
+        let watcher = Watcher::new(dir);
+        assert!(!watcher.changed());
+
+        write(dir.join("new"), "").unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+
+        remove_dir_all(dir).unwrap();
+    }
+}
@@ -95,9 +95,9 @@ fn list_build_files() {
         let mut path_cache = command_assistors::PathCache::new(&path_path);
 
         // Changing directories.
-        path_cache.switch();
+        path_cache.switch().unwrap();
 
-        path_cache.switch_back();
+        path_cache.switch_back().unwrap();
 
         assert_files(path);
 
@@ -120,10 +120,10 @@ fn list() {
         let mut path_cache = command_assistors::PathCache::new(&path_path);
 
         // Changing directories.
-        path_cache.switch();
+        path_cache.switch().unwrap();
         let _ls_key = ls_key::LsKey::new(path, list_all, false, None, None, None);
 
-        path_cache.switch_back();
+        path_cache.switch_back().unwrap();
 
         assert_files(path);
 
@@ -147,15 +147,18 @@ fn fuzzy_list() {
         let mut path_cache = command_assistors::PathCache::new(&path_path);
 
         // Changing directories.
-        path_cache.switch();
+        path_cache.switch().unwrap();
 
-        let ls_key = ls_key::LsKey::new(path, list_all, false, None, None, None);
+        let mut ls_key = ls_key::LsKey::new(path, list_all, false, None, None, None);
 
         let list_original = ls_key.list.clone();
-        let ls_key_fuzzed = ls_key.clone().fuzzy_update(input.to_string());
-        let list_fuzzed = ls_key_fuzzed.fuzzy_list.clone().unwrap();
+        ls_key.matcher.start(ls_key.list.files.clone());
+        ls_key.fuzzy_search(input);
+        ls_key.matcher.finish();
+        ls_key.fuzzy_matched();
+        let list_fuzzed = ls_key.fuzzy_list.clone().unwrap();
 
-        path_cache.switch_back();
+        path_cache.switch_back().unwrap();
 
         assert_files(path);
 
@@ -167,32 +170,9 @@ fn fuzzy_list() {
 
         assert_eq!(false, Path::new(path).exists());
 
-        assert_eq!(
-            format!("{:#?}", list_fuzzed),
-            //List {
-            //     files: ["CREDITS"],
-            //     dirs: ["security", "scripts", "crypto", "certs"],
-            //     parent_dir: Some("/tmp/lsk_tests/"),
-            //     path_history: ["/tmp/lsk_tests/"]
-            //}
-            format!("{}",
-                    "List {\n    files: [\n        Entry {\n            path: \"security\",\n            file_type: Dir,\n            key: None,\n        },\n        Entry {\n            path: \"scripts\",\n            file_type: Dir,\n            key: None,\n        },\n        Entry {\n            path: \"crypto\",\n            file_type: Dir,\n            key: None,\n        },\n        Entry {\n            path: \"certs\",\n            file_type: Dir,\n            key: None,\n        },\n        Entry {\n            path: \"CREDITS\",\n            file_type: File,\n            key: None,\n        },\n    ],\n    parent_path: \"/tmp/lsk_tests/\",\n    path_history: [\n        \"/tmp/lsk_tests/\",\n    ],\n    filter: None,\n}"
-            )
-        );
-        assert_ne!(
-            format!("{:#?}", list_original),
-            //list_fuzzed
-            format!("{}",
-                "List \
-                 {\n    \
-                     files: [\n        \"CREDITS\",\n    ],\n    \
-                     dirs: [\n        \"security\",\n        \"scripts\",\n        \"crypto\",\n        \"certs\",\n    ],\n    \
-                     parent_path: \"/tmp/lsk_tests/\",\n    \
-                     path_history: [\n        \"/tmp/lsk_tests/\",\n    ],\n\
-                 }"
-            )
-        );
-
+        let names: Vec<String> = list_fuzzed.files.iter().map(|entry| entry.path.display().to_string()).collect();
+        assert_eq!(names, vec!["security", "scripts", "crypto", "certs", "CREDITS"]);
+        assert_ne!(list_fuzzed.files, list_original.files);
 }
 
 #[test]
@@ -208,7 +188,7 @@ fn list_go_up_one_level() {
         let mut path_cache = command_assistors::PathCache::new(&path_path);
 
         // Changing directories.
-        path_cache.switch();
+        path_cache.switch().unwrap();
 
         let mut ls_key = ls_key::LsKey::new(path, list_all, false, None, None, None);
 
@@ -227,11 +207,11 @@ fn list_go_up_one_level() {
         let file_pathbuf = ls_key.list.parent_path.clone();
         ls_key.list.parent_path.pop();
         let list = ls_key.list.clone().update(file_pathbuf);
-        ls_key.update(list);
+        ls_key.update(list).unwrap();
         let list_up_level = ls_key.list;
         //self.run_list_read();
 
-        path_cache.switch_back();
+        path_cache.switch_back().unwrap();
 
         assert_files(path);
 
@@ -245,7 +225,7 @@ fn list_go_up_one_level() {
 
         assert_eq!(
             format!("{:#?}", list_up_level),
-            "List {\n    files: [\n        Entry {\n            path: \"list_enter_dir\",\n            file_type: Dir,\n            key: None,\n        },\n    ],\n    parent_path: \"/tmp/lsk_tests\",\n    path_history: [\n        \"/tmp/lsk_tests/list_enter_dir/\",\n        \"/tmp/lsk_tests\",\n    ],\n    filter: None,\n}"
+            "List {\n    files: [\n        Entry {\n            path: \"list_enter_dir\",\n            file_type: Dir,\n            key: None,\n        },\n    ],\n    parent_path: \"/tmp/lsk_tests\",\n    path_history: [\n        \"/tmp/lsk_tests/list_enter_dir/\",\n        \"/tmp/lsk_tests\",\n    ],\n    filter: None,\n    sort: Sort {\n        by: Name,\n        reverse: false,\n    },\n}"
         );
 
         assert_ne!(list_original, list_up_level);
@@ -264,7 +244,7 @@ fn list_enter_into_dir() {
         let mut path_cache = command_assistors::PathCache::new(&path_path);
 
         // Changing directories.
-        path_cache.switch();
+        path_cache.switch().unwrap();
 
         let mut ls_key = ls_key::LsKey::new(path, list_all, false, None, None, None);
 
@@ -283,14 +263,14 @@ fn list_enter_into_dir() {
         let file_pathbuf = list_original.get_file_by_key(key, !is_fuzzed).unwrap();
         if metadata(file_pathbuf.clone()).unwrap().is_dir() {
             let list = ls_key.list.clone().update(file_pathbuf);
-            ls_key.update(list);
+            ls_key.update(list).unwrap();
         } else {
             assert!(false);
         }
 
         let list_enter_usr_dir = ls_key.list;
 
-        path_cache.switch_back();
+        path_cache.switch_back().unwrap();
 
         assert_files(path);
 
@@ -304,7 +284,7 @@ fn list_enter_into_dir() {
 
         assert_eq!(
             format!("{:#?}", list_enter_usr_dir),
-            "List {\n    files: [\n        Entry {\n            path: \"Kconfig\",\n            file_type: File,\n            key: None,\n        },\n        Entry {\n            path: \"gen_init_cpio.c\",\n            file_type: File,\n            key: None,\n        },\n        Entry {\n            path: \"include\",\n            file_type: Dir,\n            key: None,\n        },\n        Entry {\n            path: \"Makefile\",\n            file_type: File,\n            key: None,\n        },\n        Entry {\n            path: \"initramfs_data.S\",\n            file_type: File,\n            key: None,\n        },\n        Entry {\n            path: \"gen_initramfs.sh\",\n            file_type: File,\n            key: None,\n        },\n        Entry {\n            path: \"default_cpio_list\",\n            file_type: File,\n            key: None,\n        },\n    ],\n    parent_path: \"/tmp/lsk_tests/list_enter_dir/usr\",\n    path_history: [\n        \"/tmp/lsk_tests/list_enter_dir/\",\n        \"/tmp/lsk_tests/list_enter_dir/usr\",\n    ],\n    filter: None,\n}"
+            "List {\n    files: [\n        Entry {\n            path: \"Kconfig\",\n            file_type: File,\n            key: None,\n        },\n        Entry {\n            path: \"gen_init_cpio.c\",\n            file_type: File,\n            key: None,\n        },\n        Entry {\n            path: \"include\",\n            file_type: Dir,\n            key: None,\n        },\n        Entry {\n            path: \"Makefile\",\n            file_type: File,\n            key: None,\n        },\n        Entry {\n            path: \"initramfs_data.S\",\n            file_type: File,\n            key: None,\n        },\n        Entry {\n            path: \"gen_initramfs.sh\",\n            file_type: File,\n            key: None,\n        },\n        Entry {\n            path: \"default_cpio_list\",\n            file_type: File,\n            key: None,\n        },\n    ],\n    parent_path: \"/tmp/lsk_tests/list_enter_dir/usr\",\n    path_history: [\n        \"/tmp/lsk_tests/list_enter_dir/\",\n        \"/tmp/lsk_tests/list_enter_dir/usr\",\n    ],\n    filter: None,\n    sort: Sort {\n        by: Name,\n        reverse: false,\n    },\n}"
         );
 
         assert_ne!(list_original, list_enter_usr_dir);
@@ -323,7 +303,7 @@ fn list_enter_into_fuzzed_dir() {
         let mut path_cache = command_assistors::PathCache::new(&path_path);
 
         // Changing directories.
-        path_cache.switch();
+        path_cache.switch().unwrap();
 
         let mut ls_key = ls_key::LsKey::new(path, list_all, false, None, None, None);
 
@@ -332,10 +312,10 @@ fn list_enter_into_fuzzed_dir() {
         let file_pathbuf = ls_key.list.parent_path.clone();
         ls_key.list.parent_path.pop();
         let list = ls_key.list.clone().update(file_pathbuf);
-        ls_key.update(list);
+        ls_key.update(list).unwrap();
         let list_up_level = ls_key.list;
 
-        path_cache.switch_back();
+        path_cache.switch_back().unwrap();
 
         assert_files(path);
 
@@ -349,7 +329,7 @@ fn list_enter_into_fuzzed_dir() {
 
         assert_eq!(
             format!("{:#?}", list_up_level),
-            "List {\n    files: [\n        Entry {\n            path: \"list_enter_dir\",\n            file_type: Dir,\n            key: None,\n        },\n    ],\n    parent_path: \"/tmp/lsk_tests\",\n    path_history: [\n        \"/tmp/lsk_tests/list_enter_dir/\",\n        \"/tmp/lsk_tests\",\n    ],\n    filter: None,\n}"
+            "List {\n    files: [\n        Entry {\n            path: \"list_enter_dir\",\n            file_type: Dir,\n            key: None,\n        },\n    ],\n    parent_path: \"/tmp/lsk_tests\",\n    path_history: [\n        \"/tmp/lsk_tests/list_enter_dir/\",\n        \"/tmp/lsk_tests\",\n    ],\n    filter: None,\n    sort: Sort {\n        by: Name,\n        reverse: false,\n    },\n}"
         );
 
         assert_ne!(list_original, list_up_level);
@@ -365,7 +345,7 @@ fn list_test_cmd_in_different_dir() {
         let mut path_cache = command_assistors::PathCache::new(&path_path);
 
         // Changing directories.
-        path_cache.switch();
+        path_cache.switch().unwrap();
 
         //let cmd = "vim";
         //let args = [""];
@@ -389,7 +369,7 @@ fn list_test_cmd_in_different_dir() {
             .wait_with_output()
             .expect("failed to wait on child");
 
-        path_cache.switch_back();
+        path_cache.switch_back().unwrap();
 
         assert_files(path);
 
@@ -473,7 +453,7 @@ mod list {
 
         assert_eq!(file_path_1, Path::new("/tmp/lsk_tests/get_non_hidden_paths_by_key/a-dir").to_path_buf());
         assert_eq!(file_path_2, Path::new("/tmp/lsk_tests/get_non_hidden_paths_by_key/a-file").to_path_buf());
-        assert_eq!(file_path_3, None);
+        assert!(file_path_3.is_err());
     }
 
     #[test]
@@ -518,6 +498,6 @@ mod list {
         assert_eq!(file_path_2, Path::new("/tmp/lsk_tests/get_all_paths_by_key/.a-hidden-file").to_path_buf());
         assert_eq!(file_path_3, Path::new("/tmp/lsk_tests/get_all_paths_by_key/a-dir").to_path_buf());
         assert_eq!(file_path_4, Path::new("/tmp/lsk_tests/get_all_paths_by_key/a-file").to_path_buf());
-        assert_eq!(file_path_5, None);
+        assert!(file_path_5.is_err());
     }
 }